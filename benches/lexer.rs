@@ -0,0 +1,73 @@
+//! Lexer throughput in MB/s on large generated inputs, for the byte-scanning
+//! `Lexer` and, as `chars-*`, the char-based core it replaced.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use openscad::lexer::baseline::CharLexer;
+use openscad::lexer::{Lexer, TokType};
+
+/// A polyhedron dump like the ones generated from meshes.
+fn polyhedron(points: usize) -> String {
+    let mut scad = String::from("// generated\npoints = [\n");
+    for i in 0..points {
+        let f = i as f64;
+        scad.push_str(&format!(
+            "    [{:.6}, {:.6}, {:e}],\n",
+            f * 0.37,
+            -f * 1.25,
+            f / 1024.0
+        ));
+    }
+    scad.push_str("];\nfaces = [\n");
+    for i in 0..points.saturating_sub(2) {
+        scad.push_str(&format!("    [{}, {}, {}],\n", i, i + 1, i + 2));
+    }
+    scad.push_str("];\npolyhedron(points = points, faces = faces);\n");
+    scad
+}
+
+/// Hand-written looking code with comments, strings and modules.
+fn library(modules: usize) -> String {
+    let mut scad = String::from("/* [Size] */\nwidth = 40; // [10:100]\n\n");
+    for i in 0..modules {
+        scad.push_str(&format!(
+            "// part number {i}\nmodule part_{i}(size = 10, label = \"Part\\t{i}\") {{\n    \
+             difference() {{\n        cube([size, size * 2, width / {i}.5], center = true);\n        \
+             #translate([0, 0, -1]) cylinder(d = size / 2, h = size + 2, $fn = 64);\n        \
+             /* engraving */ if (len(label) > 0 && size >= 5) text(label, size = 3);\n    }}\n}}\n\n",
+            i = i
+        ));
+    }
+    scad
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let inputs = vec![("polyhedron", polyhedron(100_000)), ("library", library(5_000))];
+
+    let mut group = c.benchmark_group("lex");
+    for (name, input) in &inputs {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("collect", name), input, |b, input| {
+            b.iter(|| TokType::lex(input).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("stream", name), input, |b, input| {
+            b.iter(|| Lexer::new(input).filter(Result::is_ok).count())
+        });
+        group.bench_with_input(BenchmarkId::new("lossless", name), input, |b, input| {
+            b.iter(|| Lexer::new(input).with_trivia().filter(Result::is_ok).count())
+        });
+        group.bench_with_input(BenchmarkId::new("chars-collect", name), input, |b, input| {
+            b.iter(|| CharLexer::new(input).collect::<Result<Vec<_>, _>>().unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("chars-stream", name), input, |b, input| {
+            b.iter(|| CharLexer::new(input).filter(Result::is_ok).count())
+        });
+        group.bench_with_input(BenchmarkId::new("chars-lossless", name), input, |b, input| {
+            b.iter(|| CharLexer::new(input).with_trivia().filter(Result::is_ok).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexer);
+criterion_main!(benches);
@@ -0,0 +1,288 @@
+use std::fmt;
+
+use crate::json::json_string;
+use crate::lexer::{LexError, LexType, Lexer, TokType};
+
+#[cfg(test)]
+mod test;
+
+/// Group of parameters declared before the first `/* [Group] */` header.
+pub const DEFAULT_GROUP: &str = "Parameters";
+/// Parameters below a `/* [Hidden] */` header are not shown by the Customizer.
+pub const HIDDEN_GROUP: &str = "Hidden";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Vector(Vec<f64>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Vector(v) => {
+                write!(f, "[")?;
+                for (i, n) in v.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", n)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Choice {
+    pub value: Value,
+    pub label: Option<String>,
+}
+
+/// How the Customizer lets the user edit a parameter, taken from a trailing
+/// `// [...]` comment.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Widget {
+    /// No annotation: a spin box, text box, checkbox or vector editor.
+    Default,
+    /// `// [max]`, `// [min:max]` or `// [min:step:max]`.
+    Slider { min: f64, step: Option<f64>, max: f64 },
+    /// `// [a, b, c]` or `// [10:Small, 20:Large]`.
+    Dropdown(Vec<Choice>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub value: Value,
+    pub group: String,
+    pub description: Option<String>,
+    pub widget: Widget,
+}
+
+/// Extracts the Customizer parameters of a file: top-level assignments of a
+/// literal value, together with their group, description and widget.
+pub fn parameters(src: &str) -> Result<Vec<Parameter>, LexError> {
+    let tokens = Lexer::new(src).with_trivia().collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = Vec::new();
+    let mut group = DEFAULT_GROUP.to_string();
+    let mut description: Option<String> = None;
+    let mut depth = 0usize;
+    let mut statement_start = true;
+    // a comment alone on the line directly above an assignment describes it
+    let mut line_start = true;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i].token {
+            TokType::BlockComment(text) if depth == 0 => {
+                if let Some(name) = group_header(text) {
+                    group = name.to_string();
+                    description = None;
+                }
+            }
+            TokType::Comment(text) => {
+                if line_start && depth == 0 {
+                    description = Some(text.trim().to_string());
+                }
+            }
+            TokType::Newline => {
+                if line_start {
+                    description = None;
+                }
+                line_start = true;
+                i += 1;
+                continue;
+            }
+            TokType::Whitespace => {
+                i += 1;
+                continue;
+            }
+            TokType::IDENTIFIER(name) if depth == 0 && statement_start => {
+                if let Some((value, end)) = assignment(&tokens, i) {
+                    let (widget, trailing, next) = annotation(&tokens, end);
+                    if group != HIDDEN_GROUP {
+                        result.push(Parameter {
+                            name: name.to_string(),
+                            value,
+                            group: group.clone(),
+                            description: description.take().or(trailing),
+                            widget,
+                        });
+                    }
+                    description = None;
+                    line_start = false;
+                    i = next;
+                    continue;
+                }
+                statement_start = false;
+            }
+            TokType::LBrace | TokType::LParen | TokType::LBracket => {
+                depth += 1;
+                statement_start = false;
+            }
+            TokType::RBrace | TokType::RParen | TokType::RBracket => {
+                depth = depth.saturating_sub(1);
+                statement_start = depth == 0;
+            }
+            TokType::Semicolon => statement_start = depth == 0,
+            _ => statement_start = false,
+        }
+        if !matches!(tokens[i].token, TokType::Comment(_)) {
+            description = None;
+        }
+        line_start = false;
+        i += 1;
+    }
+    Ok(result)
+}
+
+/// Renders parameters as an OpenSCAD parameter set file containing the
+/// default values as the set `name`.
+pub fn to_parameter_set_json(parameters: &[Parameter], name: &str) -> String {
+    let mut json = String::from("{\n    \"parameterSets\": {\n");
+    json.push_str(&format!("        {}: {{", json_string(name)));
+    for (i, param) in parameters.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(
+            "\n            {}: {}",
+            json_string(&param.name),
+            json_string(&param.value.to_string())
+        ));
+    }
+    if !parameters.is_empty() {
+        json.push_str("\n        ");
+    }
+    json.push_str("}\n    },\n    \"fileFormatVersion\": \"1\"\n}\n");
+    json
+}
+
+/// Name of a `/* [Group] */` header comment.
+fn group_header(text: &str) -> Option<&str> {
+    let text = text.trim();
+    if text.starts_with('[') && text.ends_with(']') {
+        Some(text[1..text.len() - 1].trim())
+    } else {
+        None
+    }
+}
+
+fn next_significant(tokens: &[LexType], mut i: usize) -> usize {
+    while i < tokens.len() && tokens[i].token.is_trivia() {
+        i += 1;
+    }
+    i
+}
+
+/// Parses `name = <literal>;` starting at the name, returning the value and
+/// the index behind the semicolon.
+fn assignment(tokens: &[LexType], name: usize) -> Option<(Value, usize)> {
+    let assign = next_significant(tokens, name + 1);
+    if tokens.get(assign)?.token != TokType::Assign {
+        return None;
+    }
+    let (value, end) = literal(tokens, next_significant(tokens, assign + 1))?;
+    let semicolon = next_significant(tokens, end);
+    if tokens.get(semicolon)?.token != TokType::Semicolon {
+        return None;
+    }
+    Some((value, semicolon + 1))
+}
+
+fn number(tokens: &[LexType], i: usize) -> Option<(f64, usize)> {
+    let (negative, i) = match tokens.get(i)?.token {
+        TokType::Minus => (true, next_significant(tokens, i + 1)),
+        _ => (false, i),
+    };
+    let n = match tokens.get(i)?.token {
+        TokType::IConstant(n) => n as f64,
+        TokType::FConstant(n) => n,
+        _ => return None,
+    };
+    Some((if negative { -n } else { n }, i + 1))
+}
+
+fn literal(tokens: &[LexType], i: usize) -> Option<(Value, usize)> {
+    match &tokens.get(i)?.token {
+        TokType::StringLiteral(_, value) => Some((Value::String(value.to_string()), i + 1)),
+        TokType::Bool(b) => Some((Value::Bool(*b), i + 1)),
+        TokType::LBracket => {
+            let mut vector = Vec::new();
+            let mut i = next_significant(tokens, i + 1);
+            if tokens.get(i)?.token == TokType::RBracket {
+                return Some((Value::Vector(vector), i + 1));
+            }
+            loop {
+                let (n, next) = number(tokens, i)?;
+                vector.push(n);
+                i = next_significant(tokens, next);
+                match tokens.get(i)?.token {
+                    TokType::Comma => i = next_significant(tokens, i + 1),
+                    TokType::RBracket => return Some((Value::Vector(vector), i + 1)),
+                    _ => return None,
+                }
+            }
+        }
+        _ => number(tokens, i).map(|(n, i)| (Value::Number(n), i)),
+    }
+}
+
+/// Reads the comment trailing an assignment on the same line, returning the
+/// widget, a description if the comment is not a widget annotation, and the
+/// index to continue at.
+fn annotation(tokens: &[LexType], mut i: usize) -> (Widget, Option<String>, usize) {
+    while let Some(TokType::Whitespace) = tokens.get(i).map(|t| &t.token) {
+        i += 1;
+    }
+    let text = match tokens.get(i).map(|t| &t.token) {
+        Some(TokType::Comment(text)) => text.trim(),
+        _ => return (Widget::Default, None, i),
+    };
+    match widget(text) {
+        Some(widget) => (widget, None, i + 1),
+        None => (Widget::Default, Some(text.to_string()), i + 1),
+    }
+}
+
+fn widget(text: &str) -> Option<Widget> {
+    let inner = group_header(text)?;
+    if inner.contains(',') {
+        let choices = inner
+            .split(',')
+            .map(|choice| {
+                let choice = choice.trim();
+                let (value, label) = match choice.find(':') {
+                    Some(colon) => (&choice[..colon], Some(choice[colon + 1..].trim().to_string())),
+                    None => (choice, None),
+                };
+                let value = value.trim();
+                let value = match value.parse::<f64>() {
+                    Ok(n) => Value::Number(n),
+                    Err(_) => Value::String(value.to_string()),
+                };
+                Choice { value, label }
+            })
+            .collect();
+        return Some(Widget::Dropdown(choices));
+    }
+    let numbers = inner
+        .split(':')
+        .map(|n| n.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    match numbers[..] {
+        [max] => Some(Widget::Slider { min: 0.0, step: None, max }),
+        [min, max] => Some(Widget::Slider { min, step: None, max }),
+        [min, step, max] => Some(Widget::Slider { min, step: Some(step), max }),
+        _ => None,
+    }
+}
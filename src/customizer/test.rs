@@ -0,0 +1,86 @@
+use super::{parameters, to_parameter_set_json, Choice, Parameter, Value, Widget};
+
+#[test]
+fn customizer() {
+    let scad = r#"// Width of the box
+width = 40; // [10:100]
+height = -2.5;
+label = "Box"; // text on the lid
+
+/* [Lid] */
+
+// Lid style
+style = "flat"; // [flat, round, hinged]
+size = 20; // [10:Small, 20:Medium, 30:Large]
+thickness = 2; // [0:0.5:5]
+hole = [1, 2, 3];
+enabled = true;
+computed = width * 2;
+
+/* [Hidden] */
+$fn = 100;
+
+module box() {
+    inner = 5; // [1:10]
+}
+"#;
+    let params = parameters(scad).unwrap();
+
+    assert_eq!(params.len(), 8);
+    assert_eq!(
+        params[0],
+        Parameter {
+            name: "width".to_string(),
+            value: Value::Number(40.0),
+            group: "Parameters".to_string(),
+            description: Some("Width of the box".to_string()),
+            widget: Widget::Slider { min: 10.0, step: None, max: 100.0 },
+        }
+    );
+    assert_eq!(params[1].value, Value::Number(-2.5));
+    assert_eq!(params[1].description, None);
+    assert_eq!(params[2].description, Some("text on the lid".to_string()));
+    assert_eq!(params[2].widget, Widget::Default);
+
+    assert_eq!(params[3].group, "Lid");
+    assert_eq!(params[3].description, Some("Lid style".to_string()));
+    assert_eq!(
+        params[3].widget,
+        Widget::Dropdown(vec![
+            Choice { value: Value::String("flat".to_string()), label: None },
+            Choice { value: Value::String("round".to_string()), label: None },
+            Choice { value: Value::String("hinged".to_string()), label: None },
+        ])
+    );
+    assert_eq!(
+        params[4].widget,
+        Widget::Dropdown(vec![
+            Choice { value: Value::Number(10.0), label: Some("Small".to_string()) },
+            Choice { value: Value::Number(20.0), label: Some("Medium".to_string()) },
+            Choice { value: Value::Number(30.0), label: Some("Large".to_string()) },
+        ])
+    );
+    assert_eq!(params[5].widget, Widget::Slider { min: 0.0, step: Some(0.5), max: 5.0 });
+    assert_eq!(params[6].value, Value::Vector(vec![1.0, 2.0, 3.0]));
+    assert_eq!(params[7].value, Value::Bool(true));
+}
+
+#[test]
+fn parameter_set_json() {
+    let params = parameters("a = 1.5;\nb = \"say \\\"hi\\\"\";\nc = [1, 2];\n").unwrap();
+
+    assert_eq!(
+        to_parameter_set_json(&params, "design default values"),
+        r#"{
+    "parameterSets": {
+        "design default values": {
+            "a": "1.5",
+            "b": "say \"hi\"",
+            "c": "[1, 2]"
+        }
+    },
+    "fileFormatVersion": "1"
+}
+"#
+    );
+}
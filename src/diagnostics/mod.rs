@@ -0,0 +1,354 @@
+use std::fmt::{self, Write};
+
+use crate::json::json_string;
+use crate::lexer::{LexError, SourceDb, Span};
+use crate::parser::{closing, ParseError};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A span of source marked by a diagnostic. The primary label points at the
+/// problem itself, secondary labels at related code.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// An error or warning about a program, with everything needed to show it to
+/// the user: the labelled source spans, notes and help texts.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Span of the first primary label, or of the first label if there is
+    /// no primary one.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .or_else(|| self.labels.first())
+            .map(|label| label.span)
+    }
+
+    /// Renders the diagnostic as a single line JSON object, for CI tools to
+    /// annotate the source with. Positions are 1-based, columns in chars.
+    pub fn to_json(&self, db: &SourceDb) -> String {
+        let mut json = format!(
+            "{{\"severity\":{},\"message\":{}",
+            json_string(&self.severity.to_string()),
+            json_string(&self.message)
+        );
+        if let Some(span) = self.primary_span() {
+            json.push(',');
+            json_location(&mut json, db, span);
+        }
+        json.push_str(",\"labels\":[");
+        for (i, label) in self.labels.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push('{');
+            json_location(&mut json, db, label.span);
+            let _ = write!(
+                json,
+                ",\"message\":{},\"primary\":{}}}",
+                json_string(&label.message),
+                label.primary
+            );
+        }
+        json.push_str("],\"notes\":[");
+        json_list(&mut json, &self.notes);
+        json.push_str("],\"help\":[");
+        json_list(&mut json, &self.help);
+        json.push_str("]}");
+        json
+    }
+}
+
+fn json_location(json: &mut String, db: &SourceDb, span: Span) {
+    let start = db.location(span);
+    let end = db.location(Span::new(span.end, span.end).in_file(span.file));
+    let _ = write!(
+        json,
+        "\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+        json_string(&start.path.display().to_string()),
+        start.line,
+        start.column,
+        end.line,
+        end.column
+    );
+}
+
+fn json_list(json: &mut String, items: &[String]) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&json_string(item));
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        let diagnostic = Diagnostic::error(err.to_string());
+        match err {
+            LexError::UnexpectedChar { span, .. } => diagnostic.with_label(*span, ""),
+            LexError::UnterminatedString { span } => diagnostic
+                .with_label(*span, "string starts here")
+                .with_help("close the string with a matching quote"),
+            LexError::UnterminatedBlockComment { span } => diagnostic
+                .with_label(*span, "comment starts here")
+                .with_help("close the comment with `*/`"),
+            LexError::InvalidNumber { span } => diagnostic.with_label(*span, ""),
+            LexError::InvalidEscape { span } => diagnostic.with_label(*span, "").with_note(
+                "valid escapes are \\t, \\n, \\r, \\\\, \\\", \\', \\xHH up to \\x7F, \\uHHHH and \\UHHHHHH",
+            ),
+            LexError::UnterminatedPath { span } => diagnostic
+                .with_label(*span, "path starts here")
+                .with_help("file paths end with `>` on the same line"),
+        }
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        Diagnostic::from(&err)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        match err {
+            ParseError::Lex(err) => Diagnostic::from(err),
+            ParseError::Expected { expected, span, .. } => {
+                Diagnostic::error(err.to_string()).with_label(*span, format!("expected {}", expected))
+            }
+            ParseError::Unclosed { delimiter, open, span, .. } => Diagnostic::error(err.to_string())
+                .with_label(*span, format!("expected `{}`", closing(*delimiter)))
+                .with_secondary_label(*open, format!("unclosed `{}`", delimiter)),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        Diagnostic::from(&err)
+    }
+}
+
+const TAB_WIDTH: usize = 4;
+
+/// Renders diagnostics the way rustc does: the message, the location of the
+/// primary span, the labelled source lines with the spans underlined, and
+/// the notes and help texts.
+///
+/// ```text
+/// error: unterminated string literal
+///  --> main.scad:2:5
+///   |
+/// 2 | y = "ab
+///   |     ^ string starts here
+///   |
+///   = help: close the string with a matching quote
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color the output with ANSI escape sequences.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(&self, out: &mut String, style: &str, text: &str) {
+        if self.color && !text.is_empty() {
+            let _ = write!(out, "\x1b[{}m{}\x1b[0m", style, text);
+        } else {
+            out.push_str(text);
+        }
+    }
+
+    pub fn render(&self, db: &SourceDb, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let severity_style = match diagnostic.severity {
+            Severity::Error => "1;31",
+            Severity::Warning => "1;33",
+        };
+        let gutter_style = "1;34";
+
+        self.paint(&mut out, severity_style, &diagnostic.severity.to_string());
+        self.paint(&mut out, "1", &format!(": {}", diagnostic.message));
+        out.push('\n');
+
+        // every label gets its own underline row below its line
+        let mut rows: Vec<(Span, usize, &Label)> = diagnostic
+            .labels
+            .iter()
+            .map(|label| {
+                let line = db.file(label.span.file).source_map().line_index(label.span.start);
+                (label.span, line, label)
+            })
+            .collect();
+        let primary = diagnostic.primary_span();
+        // the primary file comes first, then the others in order of the ids
+        rows.sort_by_key(|&(span, line, label)| {
+            let other_file = Some(span.file) != primary.map(|p| p.file);
+            (other_file, span.file, line, !label.primary, span.start)
+        });
+
+        let width = rows.iter().map(|&(_, line, _)| (line + 1).to_string().len()).max().unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        let mut previous: Option<(Span, usize)> = None;
+        for &(span, line, label) in &rows {
+            let map = db.file(span.file).source_map();
+            let new_file = previous.is_none_or(|(p, _)| p.file != span.file);
+            if new_file {
+                if previous.is_some() {
+                    self.paint(&mut out, gutter_style, &format!("{} |", gutter));
+                    out.push('\n');
+                }
+                let arrow = if previous.is_none() { "-->" } else { ":::" };
+                // the location of the primary span heads its file
+                let start = if previous.is_none() { primary.unwrap_or(span) } else { span };
+                out.push_str(&gutter);
+                self.paint(&mut out, gutter_style, arrow);
+                let _ = writeln!(out, " {}", db.location(start));
+                self.paint(&mut out, gutter_style, &format!("{} |", gutter));
+                out.push('\n');
+            }
+            let same_line = !new_file && previous.is_some_and(|(_, l)| l == line);
+            if !same_line {
+                if !new_file && previous.is_some_and(|(_, l)| line > l + 1) {
+                    self.paint(&mut out, gutter_style, "...");
+                    out.push('\n');
+                }
+                let text = &map.text()[map.line_span(line).range()];
+                self.paint(&mut out, gutter_style, &format!("{:>width$} |", line + 1, width = width));
+                if !text.is_empty() {
+                    out.push(' ');
+                    out.push_str(&text.replace('\t', &" ".repeat(TAB_WIDTH)));
+                }
+                out.push('\n');
+            }
+
+            // underline the part of the span on its first line
+            let line_span = map.line_span(line);
+            // the span may start on the line terminator, past the visible text
+            let start = span.start.min(line_span.end);
+            let text = &map.text()[line_span.start..start];
+            let column: usize = text.chars().map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum();
+            let end = span.end.clamp(start, line_span.end);
+            let underlined = map.text()[start..end]
+                .chars()
+                .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+                .sum::<usize>()
+                .max(1);
+            let (mark, style) = if label.primary { ('^', severity_style) } else { ('-', gutter_style) };
+            self.paint(&mut out, gutter_style, &format!("{} |", gutter));
+            out.push(' ');
+            out.push_str(&" ".repeat(column));
+            let mut marks: String = std::iter::repeat_n(mark, underlined).collect();
+            if !label.message.is_empty() {
+                marks.push(' ');
+                marks.push_str(&label.message);
+            }
+            self.paint(&mut out, style, &marks);
+            out.push('\n');
+            previous = Some((span, line));
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.help.is_empty() {
+            if !rows.is_empty() {
+                self.paint(&mut out, gutter_style, &format!("{} |", gutter));
+                out.push('\n');
+            }
+            for (kind, texts) in [("note", &diagnostic.notes), ("help", &diagnostic.help)] {
+                for text in texts.iter() {
+                    out.push_str(&gutter);
+                    self.paint(&mut out, gutter_style, " =");
+                    out.push(' ');
+                    self.paint(&mut out, "1", kind);
+                    let _ = writeln!(out, ": {}", text);
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,138 @@
+use super::{Diagnostic, Renderer};
+use crate::lexer::{LexError, SourceDb, Span};
+use crate::parser::parse_file_recovering;
+
+#[test]
+fn render() {
+    let mut db = SourceDb::new();
+    let main = db.add("main.scad", "x = 1;\ny = \"ab\n");
+    let (_, errors) = db.lex(main);
+    let diagnostic = Diagnostic::from(&errors[0]);
+
+    assert_eq!(
+        Renderer::new().render(&db, &diagnostic),
+        "error: unterminated string literal
+ --> main.scad:2:5
+  |
+2 | y = \"ab
+  |     ^ string starts here
+  |
+  = help: close the string with a matching quote
+"
+    );
+    assert_eq!(
+        Renderer::new().with_color(true).render(&db, &diagnostic).lines().next(),
+        Some("\x1b[1;31merror\x1b[0m\x1b[1m: unterminated string literal\x1b[0m")
+    );
+}
+
+#[test]
+fn render_unclosed() {
+    let mut db = SourceDb::new();
+    let main = db.add("main.scad", "translate([0, 0, 1)\n  cube(2;\n");
+    let (_, errors) = parse_file_recovering(&db, main);
+    let rendered: Vec<_> = errors.iter().map(|err| Renderer::new().render(&db, &Diagnostic::from(err))).collect();
+
+    assert_eq!(
+        rendered,
+        [
+            "error: expected `]` to close `[` opened at 1:11
+ --> main.scad:1:19
+  |
+1 | translate([0, 0, 1)
+  |                   ^ expected `]`
+  |           - unclosed `[`
+",
+            "error: expected `)` to close `(` opened at 2:7
+ --> main.scad:2:9
+  |
+2 |   cube(2;
+  |         ^ expected `)`
+  |       - unclosed `(`
+",
+        ]
+    );
+}
+
+#[test]
+fn render_line_terminators() {
+    // a span at the end of a file ending in a bare `\r`
+    let mut db = SourceDb::new();
+    let main = db.add("main.scad", "cube(1\r");
+    let (_, errors) = parse_file_recovering(&db, main);
+    assert_eq!(
+        Renderer::new().render(&db, &Diagnostic::from(&errors[0])),
+        "error: expected `)` to close `(` opened at 1:5
+ --> main.scad:1:8
+  |
+1 | cube(1
+  |       ^ expected `)`
+  |     - unclosed `(`
+"
+    );
+
+    // a label on the `\n` of a CRLF line
+    let main = db.add("crlf.scad", "x = 1\r\ny = 2;\r\n");
+    let diagnostic = Diagnostic::error("missing `;`").with_label(Span::new(6, 7).in_file(main), "expected `;`");
+    assert_eq!(
+        Renderer::new().render(&db, &diagnostic),
+        "error: missing `;`
+ --> crlf.scad:1:7
+  |
+1 | x = 1
+  |      ^ expected `;`
+"
+    );
+}
+
+#[test]
+fn render_labels() {
+    let mut db = SourceDb::new();
+    let lib = db.add("lib.scad", "module m(size) {\n\tcube(size);\n}\n");
+    let main = db.add("main.scad", "use <lib.scad>\nm(\n  1,\n  2,\n  3,\n  4\n);\n");
+
+    let diagnostic = Diagnostic::warning("too many arguments")
+        .with_label(Span::new(25, 26).in_file(main), "unused argument")
+        .with_secondary_label(Span::new(15, 17).in_file(main), "called here")
+        .with_secondary_label(Span::new(0, 14).in_file(lib), "module defined with 1 parameter")
+        .with_secondary_label(Span::new(18, 28).in_file(lib), "")
+        .with_note("`m` takes `size`");
+
+    assert_eq!(
+        Renderer::new().render(&db, &diagnostic),
+        "warning: too many arguments
+ --> main.scad:4:3
+  |
+2 | m(
+  | -- called here
+...
+4 |   2,
+  |   ^ unused argument
+  |
+ ::: lib.scad:1:1
+  |
+1 | module m(size) {
+  | -------------- module defined with 1 parameter
+2 |     cube(size);
+  |     ----------
+  |
+  = note: `m` takes `size`
+"
+    );
+}
+
+#[test]
+fn json() {
+    let mut db = SourceDb::new();
+    let main = db.add("dir/main.scad", "a = \"\\q\";\n");
+    let diagnostic = Diagnostic::from(LexError::InvalidEscape { span: Span::new(5, 7).in_file(main) });
+
+    assert_eq!(
+        diagnostic.to_json(&db),
+        r#"{"severity":"error","message":"invalid escape sequence","file":"dir/main.scad","line":1,"column":6,"end_line":1,"end_column":8,"labels":[{"file":"dir/main.scad","line":1,"column":6,"end_line":1,"end_column":8,"message":"","primary":true}],"notes":["valid escapes are \\t, \\n, \\r, \\\\, \\\", \\', \\xHH up to \\x7F, \\uHHHH and \\UHHHHHH"],"help":[]}"#
+    );
+    assert_eq!(
+        Diagnostic::error("no input").to_json(&db),
+        r#"{"severity":"error","message":"no input","labels":[],"notes":[],"help":[]}"#
+    );
+}
@@ -0,0 +1,230 @@
+use crate::lexer::{Lexer, Span, TokType};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Category {
+    Keyword,
+    BuiltinModule,
+    BuiltinFunction,
+    SpecialVariable,
+    Number,
+    String,
+    Comment,
+    Operator,
+    Modifier,
+    Error,
+}
+
+impl Category {
+    pub fn css_class(self) -> &'static str {
+        match self {
+            Category::Keyword => "scad-keyword",
+            Category::BuiltinModule => "scad-module",
+            Category::BuiltinFunction => "scad-function",
+            Category::SpecialVariable => "scad-special",
+            Category::Number => "scad-number",
+            Category::String => "scad-string",
+            Category::Comment => "scad-comment",
+            Category::Operator => "scad-operator",
+            Category::Modifier => "scad-modifier",
+            Category::Error => "scad-error",
+        }
+    }
+
+    /// SGR parameters of the ANSI escape sequence coloring this category.
+    pub fn ansi_style(self) -> &'static str {
+        match self {
+            Category::Keyword => "1;35",
+            Category::BuiltinModule => "36",
+            Category::BuiltinFunction => "34",
+            Category::SpecialVariable => "33",
+            Category::Number => "32",
+            Category::String => "32",
+            Category::Comment => "90",
+            Category::Operator => "37",
+            Category::Modifier => "1;31",
+            Category::Error => "4;31",
+        }
+    }
+}
+
+/// Default colors for the classes used by `html_fragment`.
+pub const CSS: &str = "\
+pre.scad { background: #fafafa; color: #24292e; padding: 0.5em; }
+.scad-keyword { color: #a626a4; font-weight: bold; }
+.scad-module { color: #0184bc; }
+.scad-function { color: #4078f2; }
+.scad-special { color: #c18401; }
+.scad-number { color: #986801; }
+.scad-string { color: #50a14f; }
+.scad-comment { color: #a0a1a7; font-style: italic; }
+.scad-operator { color: #383a42; }
+.scad-modifier { color: #e45649; font-weight: bold; }
+.scad-error { color: #e45649; text-decoration: underline wavy; }
+";
+
+pub const BUILTIN_MODULES: &[&str] = &[
+    "children", "circle", "color", "cube", "cylinder", "difference", "group", "hull", "import",
+    "intersection", "linear_extrude", "minkowski", "mirror", "multmatrix", "offset", "polygon",
+    "polyhedron", "projection", "render", "resize", "rotate", "rotate_extrude", "scale", "sphere",
+    "square", "surface", "text", "translate", "union",
+];
+
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "abs", "acos", "asin", "atan", "atan2", "ceil", "chr", "concat", "cos", "cross", "exp",
+    "floor", "is_bool", "is_function", "is_list", "is_num", "is_string", "is_undef", "len", "ln",
+    "log", "lookup", "max", "min", "norm", "ord", "parent_module", "pow", "rands", "round",
+    "search", "sign", "sin", "sqrt", "str", "tan", "version", "version_num",
+];
+
+fn category(token: &TokType) -> Option<Category> {
+    let category = match token {
+        TokType::Module
+        | TokType::Function
+        | TokType::IF
+        | TokType::ELSE
+        | TokType::Let
+        | TokType::Each
+        | TokType::For
+        | TokType::IntersectionFor
+        | TokType::Assert
+        | TokType::Echo
+        | TokType::Include
+        | TokType::Use
+        | TokType::Bool(_)
+        | TokType::Undef => Category::Keyword,
+        TokType::IDENTIFIER(name) if name.starts_with('$') => Category::SpecialVariable,
+        TokType::IDENTIFIER(name) if BUILTIN_MODULES.contains(&name.as_ref()) => Category::BuiltinModule,
+        TokType::IDENTIFIER(name) if BUILTIN_FUNCTIONS.contains(&name.as_ref()) => Category::BuiltinFunction,
+        TokType::IConstant(_) | TokType::FConstant(_) => Category::Number,
+        TokType::StringLiteral(..) => Category::String,
+        TokType::Comment(_) | TokType::BlockComment(_) => Category::Comment,
+        TokType::RootModifier
+        | TokType::DisableModifier
+        | TokType::BackgroundModifier
+        | TokType::DebugModifier => Category::Modifier,
+        TokType::Assign
+        | TokType::Lt
+        | TokType::Gt
+        | TokType::Minus
+        | TokType::Tilde
+        | TokType::Exclamation
+        | TokType::Plus
+        | TokType::Multi
+        | TokType::Splash
+        | TokType::QuestionMark
+        | TokType::Colon
+        | TokType::SingleAnd
+        | TokType::InclusiveOr
+        | TokType::ExclusiveOr
+        | TokType::Mod
+        | TokType::LeftOp
+        | TokType::RightOp
+        | TokType::LeOp
+        | TokType::GeOp
+        | TokType::EqOp
+        | TokType::NeOp
+        | TokType::AndOp
+        | TokType::OrOp => Category::Operator,
+        TokType::Error => Category::Error,
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Classifies the source into highlighted spans, in source order. Text not
+/// covered by a span, like identifiers and brackets, is left plain.
+pub fn highlight(src: &str) -> Vec<(Span, Category)> {
+    let (tokens, _) = Lexer::new(src).with_trivia().lex_recovering();
+    let mut result = Vec::new();
+    for tok in tokens {
+        match tok.token {
+            // `include <path>` is a single token: keyword and path
+            TokType::IncludePath(_) | TokType::UsePath(_) => {
+                let text = &src[tok.span.range()];
+                let keyword = text.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(text.len());
+                let path = text.find('<').unwrap_or(keyword);
+                result.push((Span::new(tok.span.start, tok.span.start + keyword), Category::Keyword));
+                result.push((Span::new(tok.span.start + path, tok.span.end), Category::String));
+            }
+            ref token => {
+                if let Some(category) = category(token) {
+                    result.push((tok.span, category));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Renders the source with ANSI color escape sequences for a terminal.
+pub fn to_ansi(src: &str) -> String {
+    render(src, |out, text, category| match category {
+        Some(category) => {
+            out.push_str("\x1b[");
+            out.push_str(category.ansi_style());
+            out.push('m');
+            out.push_str(text);
+            out.push_str("\x1b[0m");
+        }
+        None => out.push_str(text),
+    })
+}
+
+/// Renders the source as a `<pre class="scad">` element with a CSS class on
+/// every highlighted span, see `CSS`.
+pub fn html_fragment(src: &str) -> String {
+    let mut html = String::from("<pre class=\"scad\"><code>");
+    html.push_str(&render(src, |out, text, category| match category {
+        Some(category) => {
+            out.push_str("<span class=\"");
+            out.push_str(category.css_class());
+            out.push_str("\">");
+            escape_html(out, text);
+            out.push_str("</span>");
+        }
+        None => escape_html(out, text),
+    }));
+    html.push_str("</code></pre>");
+    html
+}
+
+/// Renders the source as a standalone HTML document including the `CSS`.
+pub fn html_document(src: &str, title: &str) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    escape_html(&mut html, title);
+    html.push_str("</title>\n<style>\n");
+    html.push_str(CSS);
+    html.push_str("</style>\n</head>\n<body>\n");
+    html.push_str(&html_fragment(src));
+    html.push_str("\n</body>\n</html>\n");
+    html
+}
+
+fn render(src: &str, mut write: impl FnMut(&mut String, &str, Option<Category>)) -> String {
+    let mut out = String::with_capacity(src.len() * 2);
+    let mut pos = 0;
+    for (span, category) in highlight(src) {
+        if pos < span.start {
+            write(&mut out, &src[pos..span.start], None);
+        }
+        write(&mut out, &src[span.range()], Some(category));
+        pos = span.end;
+    }
+    write(&mut out, &src[pos..], None);
+    out
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
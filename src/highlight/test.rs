@@ -0,0 +1,53 @@
+use super::{highlight, html_document, html_fragment, to_ansi, Category};
+use crate::lexer::Span;
+
+#[test]
+fn categories() {
+    let scad = "include <lib.scad>\n// part\nmodule p(h=1.5) { #cube([h, 2, $fn]); x = len(\"a<b\") > 0 ? 1 : undef; }";
+    let spans = highlight(scad);
+    let text: Vec<(&str, Category)> = spans.iter().map(|(span, c)| (&scad[span.range()], *c)).collect();
+
+    assert_eq!(
+        text,
+        vec![
+            ("include", Category::Keyword),
+            ("<lib.scad>", Category::String),
+            ("// part", Category::Comment),
+            ("module", Category::Keyword),
+            ("=", Category::Operator),
+            ("1.5", Category::Number),
+            ("#", Category::Modifier),
+            ("cube", Category::BuiltinModule),
+            ("2", Category::Number),
+            ("$fn", Category::SpecialVariable),
+            ("=", Category::Operator),
+            ("len", Category::BuiltinFunction),
+            ("\"a<b\"", Category::String),
+            (">", Category::Operator),
+            ("0", Category::Number),
+            ("?", Category::Operator),
+            ("1", Category::Number),
+            (":", Category::Operator),
+            ("undef", Category::Keyword),
+        ]
+    );
+    assert_eq!(highlight("a § b"), vec![(Span::new(2, 4), Category::Error)]);
+}
+
+#[test]
+fn render() {
+    let scad = "cube(1); // <1>";
+
+    assert_eq!(to_ansi(scad), "\x1b[36mcube\x1b[0m(\x1b[32m1\x1b[0m); \x1b[90m// <1>\x1b[0m");
+    assert_eq!(
+        html_fragment(scad),
+        "<pre class=\"scad\"><code><span class=\"scad-module\">cube</span>(<span class=\"scad-number\">1</span>); \
+         <span class=\"scad-comment\">// &lt;1&gt;</span></code></pre>"
+    );
+
+    let html = html_document(scad, "A & B");
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>A &amp; B</title>"));
+    assert!(html.contains(".scad-module {"));
+    assert!(html.contains(&html_fragment(scad)));
+}
@@ -0,0 +1,338 @@
+//! The char-based lexer core the byte-scanning `Lexer` replaced, kept as
+//! the baseline of the lexer benchmarks and to check that both produce the
+//! same tokens. Not meant to be used otherwise.
+
+use std::borrow::Cow;
+
+use super::{is_identifier_continue, is_identifier_start, FileId, LexError, LexType, ModifierClassifier, Span, TokType};
+
+/// Lexes `src` decoding one char at a time, and parses integers again from
+/// their text once scanned.
+#[derive(Debug, Clone)]
+pub struct CharLexer<'src> {
+    src: &'src str,
+    pos: usize,
+    done: bool,
+    trivia: bool,
+    modifiers: ModifierClassifier,
+    file: FileId,
+}
+
+impl<'src> CharLexer<'src> {
+    pub fn new(src: &'src str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            done: false,
+            trivia: false,
+            modifiers: ModifierClassifier::new(),
+            file: FileId::default(),
+        }
+    }
+
+    /// Also yield `Comment`, `BlockComment`, `Whitespace` and `Newline` tokens.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek_char() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_while(&mut self, f: impl Fn(char) -> bool) {
+        while let Some(c) = self.peek_char() {
+            if !f(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn span(&self, start: usize) -> Span {
+        Span::new(start, self.pos).in_file(self.file)
+    }
+
+    fn lex_next(&mut self) -> Option<Result<LexType<'src>, LexError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let start = self.pos;
+            let c = match self.bump() {
+                Some(c) => c,
+                None => {
+                    self.done = true;
+                    return Some(Ok(LexType::new(TokType::EOF, self.span(start))));
+                }
+            };
+            let token = match c {
+                '"' | '\'' => match self.lex_string(start, c) {
+                    Ok(token) => token,
+                    Err(err) => return Some(Err(err)),
+                },
+                '0'..='9' | '.' if c != '.' || self.peek_char().is_some_and(|c| c.is_ascii_digit()) => {
+                    match self.lex_number(start) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                c if is_identifier_start(c) => match self.lex_identifier(start) {
+                    TokType::Include => match self.lex_path(TokType::Include, TokType::IncludePath) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    TokType::Use => match self.lex_path(TokType::Use, TokType::UsePath) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    token => token,
+                },
+                '\n' => TokType::Newline,
+                '\r' if self.eat('\n') => TokType::Newline,
+                ' ' | '\t' | '\r' => {
+                    while let Some(c) = self.peek_char() {
+                        let blank = match c {
+                            ' ' | '\t' => true,
+                            '\r' => !self.src[self.pos + 1..].starts_with('\n'),
+                            _ => false,
+                        };
+                        if !blank {
+                            break;
+                        }
+                        self.bump();
+                    }
+                    TokType::Whitespace
+                }
+                '/' if self.eat('/') => {
+                    self.eat_while(|c| c != '\n');
+                    let mut end = self.pos;
+                    if self.src[..end].ends_with('\r') {
+                        // keep the \r\n together as a Newline
+                        end -= 1;
+                        self.pos = end;
+                    }
+                    TokType::Comment(Cow::Borrowed(&self.src[start + 2..end]))
+                }
+                '/' if self.eat('*') => match self.src[self.pos..].find("*/") {
+                    Some(end) => {
+                        let text = &self.src[self.pos..self.pos + end];
+                        self.pos += end + 2;
+                        TokType::BlockComment(Cow::Borrowed(text))
+                    }
+                    None => {
+                        self.pos = self.src.len();
+                        let span = Span::new(start, start + 2).in_file(self.file);
+                        return Some(Err(LexError::UnterminatedBlockComment { span }));
+                    }
+                },
+                '(' => TokType::LParen,
+                ')' => TokType::RParen,
+                '{' => TokType::LBrace,
+                '}' => TokType::RBrace,
+                '[' => TokType::LBracket,
+                ']' => TokType::RBracket,
+                ';' => TokType::Semicolon,
+                '~' => TokType::Tilde,
+                '?' => TokType::QuestionMark,
+                ':' => TokType::Colon,
+                ',' => TokType::Comma,
+                '#' => TokType::DebugModifier,
+                '.' => TokType::Dot,
+                '=' if self.eat('=') => TokType::EqOp,
+                '=' => TokType::Assign,
+                '<' if self.eat('=') => TokType::LeOp,
+                '<' if self.eat('<') => TokType::LeftOp,
+                '<' => TokType::Lt,
+                '>' if self.eat('=') => TokType::GeOp,
+                '>' if self.eat('>') => TokType::RightOp,
+                '>' => TokType::Gt,
+                '-' => TokType::Minus,
+                '!' if self.eat('=') => TokType::NeOp,
+                '!' => TokType::Exclamation,
+                '+' => TokType::Plus,
+                '*' => TokType::Multi,
+                '/' => TokType::Splash,
+                '%' => TokType::Mod,
+                '&' if self.eat('&') => TokType::AndOp,
+                '&' => TokType::SingleAnd,
+                '|' if self.eat('|') => TokType::OrOp,
+                '|' => TokType::InclusiveOr,
+                '^' => TokType::ExclusiveOr,
+                _ => return Some(Err(LexError::UnexpectedChar { ch: c, span: self.span(start) })),
+            };
+            if token.is_trivia() && !self.trivia {
+                continue;
+            }
+            let token = self.modifiers.classify(token);
+            return Some(Ok(LexType::new(token, self.span(start))));
+        }
+    }
+
+    fn lex_string(&mut self, start: usize, quote: char) -> Result<TokType<'src>, LexError> {
+        let src = self.src;
+        let content = start + quote.len_utf8();
+        let mut end = None;
+        let mut value: Option<String> = None;
+        let mut error = None;
+        while let Some(c) = self.bump() {
+            if c == quote {
+                end = Some(self.pos - c.len_utf8());
+                break;
+            }
+            if c == '\\' {
+                let escape = self.pos - 1;
+                let value = value.get_or_insert_with(|| src[content..escape].to_string());
+                match self.lex_escape(escape) {
+                    Ok(c) => value.push(c),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                    }
+                }
+            } else if let Some(value) = &mut value {
+                value.push(c);
+            }
+        }
+        let end = match end {
+            Some(end) => end,
+            None => {
+                let span = Span::new(start, content).in_file(self.file);
+                return Err(LexError::UnterminatedString { span });
+            }
+        };
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let raw = &src[content..end];
+        let value = match value {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(raw),
+        };
+        Ok(TokType::StringLiteral(Cow::Borrowed(raw), value))
+    }
+
+    fn lex_escape(&mut self, start: usize) -> Result<char, LexError> {
+        let (digits, max) = match self.bump() {
+            Some('t') => return Ok('\t'),
+            Some('n') => return Ok('\n'),
+            Some('r') => return Ok('\r'),
+            Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => return Ok(c),
+            Some('x') => (2, 0x7f),
+            Some('u') => (4, 0xffff),
+            Some('U') => (6, 0x10_ffff),
+            _ => return Err(LexError::InvalidEscape { span: self.span(start) }),
+        };
+        let hex = self.pos;
+        for _ in 0..digits {
+            match self.peek_char() {
+                Some(c) if c.is_ascii_hexdigit() => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+        let code = u32::from_str_radix(&self.src[hex..self.pos], 16).ok();
+        match code.filter(|&code| code > 0 && code <= max).and_then(std::char::from_u32) {
+            Some(c) if self.pos - hex == digits => Ok(c),
+            _ => Err(LexError::InvalidEscape { span: self.span(start) }),
+        }
+    }
+
+    fn lex_number(&mut self, start: usize) -> Result<TokType<'src>, LexError> {
+        self.pos = start;
+        self.eat_while(|c| c.is_ascii_digit());
+        let mut float = false;
+        if self.eat('.') {
+            float = true;
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+        if let Some('e') | Some('E') = self.peek_char() {
+            float = true;
+            self.bump();
+            if !self.eat('+') {
+                self.eat('-');
+            }
+            let digits = self.pos;
+            self.eat_while(|c| c.is_ascii_digit());
+            if self.pos == digits {
+                return Err(LexError::InvalidNumber { span: self.span(start) });
+            }
+        }
+
+        let s = &self.src[start..self.pos];
+        if !float {
+            if let Ok(number) = s.parse::<i64>() {
+                return Ok(TokType::IConstant(number));
+            }
+        }
+        match s.parse::<f64>() {
+            Ok(number) => Ok(TokType::FConstant(number)),
+            Err(_) => Err(LexError::InvalidNumber { span: self.span(start) }),
+        }
+    }
+
+    fn lex_identifier(&mut self, start: usize) -> TokType<'src> {
+        self.eat_while(is_identifier_continue);
+        match &self.src[start..self.pos] {
+            "module" => TokType::Module,
+            "function" => TokType::Function,
+            "if" => TokType::IF,
+            "else" => TokType::ELSE,
+            "let" => TokType::Let,
+            "each" => TokType::Each,
+            "for" => TokType::For,
+            "intersection_for" => TokType::IntersectionFor,
+            "assert" => TokType::Assert,
+            "echo" => TokType::Echo,
+            "include" => TokType::Include,
+            "use" => TokType::Use,
+            "true" => TokType::Bool(true),
+            "false" => TokType::Bool(false),
+            "undef" => TokType::Undef,
+            s => TokType::IDENTIFIER(Cow::Borrowed(s)),
+        }
+    }
+
+    fn lex_path(
+        &mut self,
+        keyword: TokType<'src>,
+        path: fn(Cow<'src, str>) -> TokType<'src>,
+    ) -> Result<TokType<'src>, LexError> {
+        let keyword_end = self.pos;
+        self.eat_while(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n');
+        let open = self.pos;
+        if !self.eat('<') {
+            self.pos = keyword_end;
+            return Ok(keyword);
+        }
+        self.eat_while(|c| c != '>' && c != '\t' && c != '\r' && c != '\n');
+        let end = self.pos;
+        if !self.eat('>') {
+            return Err(LexError::UnterminatedPath { span: Span::new(open, open + 1).in_file(self.file) });
+        }
+        Ok(path(Cow::Borrowed(&self.src[open + 1..end])))
+    }
+}
+
+impl<'src> Iterator for CharLexer<'src> {
+    type Item = Result<LexType<'src>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lex_next()
+    }
+}
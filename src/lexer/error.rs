@@ -0,0 +1,64 @@
+use std::{error, fmt};
+
+use super::{FileId, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LexError {
+    UnexpectedChar { ch: char, span: Span },
+    UnterminatedString { span: Span },
+    UnterminatedBlockComment { span: Span },
+    InvalidNumber { span: Span },
+    InvalidEscape { span: Span },
+    UnterminatedPath { span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match *self {
+            LexError::UnexpectedChar { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedBlockComment { span }
+            | LexError::InvalidNumber { span }
+            | LexError::InvalidEscape { span }
+            | LexError::UnterminatedPath { span } => span,
+        }
+    }
+
+    pub fn file(&self) -> FileId {
+        self.span().file
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            LexError::UnexpectedChar { span, .. }
+            | LexError::UnterminatedString { span }
+            | LexError::UnterminatedBlockComment { span }
+            | LexError::InvalidNumber { span }
+            | LexError::InvalidEscape { span }
+            | LexError::UnterminatedPath { span } => span,
+        }
+    }
+
+    /// Moves the error by `delta` bytes, for text inserted or removed before it.
+    pub(crate) fn shifted(mut self, delta: isize) -> Self {
+        let span = self.span_mut();
+        *span = span.shifted(delta);
+        self
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::UnexpectedChar { ch, .. } => write!(f, "unexpected character `{}`", ch),
+            LexError::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            LexError::UnterminatedBlockComment { .. } => write!(f, "unterminated block comment"),
+            LexError::InvalidNumber { .. } => write!(f, "invalid number literal"),
+            LexError::InvalidEscape { .. } => write!(f, "invalid escape sequence"),
+            LexError::UnterminatedPath { .. } => write!(f, "unterminated file path, expected `>`"),
+        }
+    }
+}
+
+impl error::Error for LexError {}
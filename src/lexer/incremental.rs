@@ -0,0 +1,166 @@
+use std::ops::Range;
+
+use super::{LexError, LexType, Lexer, ModifierClassifier, OwnedLexType, Span, TokType};
+
+/// Replacement of the byte range `range` of the old text by `text`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextEdit<'a> {
+    pub range: Span,
+    pub text: &'a str,
+}
+
+impl<'a> TextEdit<'a> {
+    pub fn new(range: Span, text: &'a str) -> Self {
+        Self { range, text }
+    }
+
+    fn delta(&self) -> isize {
+        self.text.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Keeps the tokens of an editor buffer up to date, re-lexing only the part
+/// of the text affected by each edit.
+///
+/// Like `TokType::lex_recovering`, bad input becomes `Error` tokens and the
+/// errors are collected.
+#[derive(Debug, Clone)]
+pub struct IncrementalLexer {
+    tokens: Vec<OwnedLexType>,
+    errors: Vec<LexError>,
+    /// Modifier context in front of each token.
+    modifiers: Vec<ModifierClassifier>,
+    trivia: bool,
+}
+
+impl IncrementalLexer {
+    pub fn new(src: &str) -> Self {
+        Self::lex(src, false)
+    }
+
+    /// Like `new`, but keeps comment and whitespace trivia tokens.
+    pub fn with_trivia(src: &str) -> Self {
+        Self::lex(src, true)
+    }
+
+    fn lex(src: &str, trivia: bool) -> Self {
+        let mut lexer = Self {
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            modifiers: Vec::new(),
+            trivia,
+        };
+        lexer.relex(src, &TextEdit::new(Span::new(0, 0), src));
+        lexer
+    }
+
+    pub fn tokens(&self) -> &[OwnedLexType] {
+        &self.tokens
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Updates the tokens after `edit` turned the old text into `src`, and
+    /// returns the index range of the tokens that were replaced.
+    ///
+    /// Lexing restarts at the last significant token before the edit,
+    /// because the lexer may have looked ahead past that token's end and any
+    /// trivia following it, like `include` does for its `<path>`, and stops as soon as a new token
+    /// starts where a token behind the edit used to start: from such a token
+    /// boundary on the text is unchanged, so are its tokens. Only whether
+    /// `!`, `*` and `%` are modifiers may change behind that point, so the
+    /// old tokens are reclassified until the modifier context is the same
+    /// as before the edit.
+    pub fn relex(&mut self, src: &str, edit: &TextEdit) -> Range<usize> {
+        let delta = edit.delta();
+        let shift = |span: Span| span.shifted(delta);
+
+        // without trivia tokens the skipped comments before the first
+        // affected token have to be lexed again too
+        let mut first = match self.tokens.iter().position(|t| t.span.end >= edit.range.start) {
+            Some(0) | None => 0,
+            Some(affected) => affected - 1,
+        };
+        while first > 0 && self.tokens[first].token.is_trivia() {
+            first -= 1;
+        }
+        let restart = if first == 0 { 0 } else { self.tokens[first].span.start };
+        trace!("relexing from token {} at {}", first, restart);
+
+        let context = self.modifiers.get(first).copied().unwrap_or_else(ModifierClassifier::new);
+        let mut lexer = Lexer::new(src).starting_at(restart, context);
+        if self.trivia {
+            lexer = lexer.with_trivia();
+        }
+        let mut tokens: Vec<LexType> = Vec::new();
+        let mut modifiers = Vec::new();
+        let mut errors = Vec::new();
+        let mut old = first;
+        let mut resync = self.tokens.len();
+        let mut context;
+        loop {
+            context = lexer.modifiers;
+            let tok = match lexer.next() {
+                Some(tok) => tok,
+                None => break,
+            };
+            let (tok, err) = match tok {
+                Ok(tok) => (tok, None),
+                Err(err) => (LexType::new(TokType::Error, lexer.span(lexer.token_start)), Some(err)),
+            };
+            if tok.span.start >= edit.range.start + edit.text.len() {
+                while old < self.tokens.len()
+                    && (self.tokens[old].span.start < edit.range.end
+                        || shift(self.tokens[old].span).start < tok.span.start)
+                {
+                    old += 1;
+                }
+                if old < self.tokens.len()
+                    && self.tokens[old].span.start >= edit.range.end
+                    && shift(self.tokens[old].span).start == tok.span.start
+                {
+                    resync = old;
+                    break;
+                }
+            }
+            tokens.push(tok);
+            modifiers.push(context);
+            errors.extend(err);
+        }
+        debug!("relexed {} tokens, resynchronised at {}", tokens.len(), resync);
+
+        // errors of the replaced tokens are dropped, the ones behind shifted
+        let stop = self.tokens.get(resync).map_or(usize::MAX, |t| t.span.start);
+        let mut kept = Vec::with_capacity(self.errors.len() + errors.len());
+        let mut behind = Vec::new();
+        for err in self.errors.drain(..) {
+            let start = err.span().start;
+            if start < restart {
+                kept.push(err);
+            } else if start >= stop {
+                behind.push(err.shifted(delta));
+            }
+        }
+        kept.extend(errors);
+        kept.extend(behind);
+        self.errors = kept;
+
+        for tok in &mut self.tokens[resync..] {
+            tok.span = shift(tok.span);
+        }
+        let mut reclassified = resync;
+        while reclassified < self.tokens.len() && self.modifiers[reclassified] != context {
+            self.modifiers[reclassified] = context;
+            let tok = &mut self.tokens[reclassified];
+            tok.token = context.reclassify(std::mem::replace(&mut tok.token, TokType::EOF));
+            reclassified += 1;
+        }
+        let relexed = tokens.len();
+        self.tokens
+            .splice(first..resync, tokens.into_iter().map(LexType::into_owned));
+        self.modifiers.splice(first..resync, modifiers);
+        first..first + relexed + (reclassified - resync)
+    }
+}
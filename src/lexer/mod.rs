@@ -1,23 +1,49 @@
 
+use std::borrow::Cow;
+
 #[cfg(test)]
 mod test;
 
+#[doc(hidden)]
+pub mod baseline;
+mod error;
+mod incremental;
+mod modifier;
+mod source;
+mod span;
+
+pub use error::LexError;
+pub use incremental::{IncrementalLexer, TextEdit};
+use modifier::ModifierClassifier;
+pub use source::{Location, SourceDb, SourceFile};
+pub use span::{FileId, SourceMap, Span};
+
+/// A token borrowing its text from the lexed source.
 #[derive(Debug, PartialEq, Clone)]
-pub struct LexType {
-    pub token: TokType,
-    pub line: usize,
-    pub collum: usize,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LexType<'src> {
+    pub token: TokType<'src>,
+    pub span: Span,
 }
 
-impl LexType {
-    pub fn new(token: TokType, line: usize, collum: usize) -> Self {
-        Self { token, line, collum}
+/// A token that does not borrow from the source.
+pub type OwnedLexType = LexType<'static>;
+
+impl<'src> LexType<'src> {
+    pub fn new(token: TokType<'src>, span: Span) -> Self {
+        Self { token, span }
+    }
+
+    pub fn into_owned(self) -> OwnedLexType {
+        LexType::new(self.token.into_owned(), self.span)
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
 #[allow(dead_code)]
-pub enum TokType {
+pub enum TokType<'src> {
     LBrace,       // {
     RBrace,       // }
     LParen,       // (
@@ -42,575 +68,619 @@ pub enum TokType {
     InclusiveOr,  // |
     ExclusiveOr,  // ^
     Mod,          // %
-    Highlight,    // #
-    IDENTIFIER(String),
+    IDENTIFIER(Cow<'src, str>),
     IConstant(i64),
     FConstant(f64),
-    StringLiteral(String, String),
-    Module,
+    StringLiteral(Cow<'src, str>, Cow<'src, str>),
+    Comment(Cow<'src, str>),      // text of a // comment
+    BlockComment(Cow<'src, str>), // text of a /* */ comment
+    Whitespace,
+    Newline,
+    IncludePath(Cow<'src, str>), // include <path>
+    UsePath(Cow<'src, str>),     // use <path>
+    Bool(bool),  // true, false
+    Undef,       // undef
+    Error,       // input the lexer could not make sense of
+
+    RootModifier,       // ! in front of a statement
+    DisableModifier,    // * in front of a statement
+    BackgroundModifier, // % in front of a statement
+    DebugModifier,      // #
 
-    // FuncName,    // __func__
-    // SIZEOF,      // sizeof
-    // PtrOp,       // ->
-    IncOp,       // ++
-    DecOp,       // --
     LeftOp,      // <<
     RightOp,     // >>
     LeOp,        // <=
-    GeOp,        // >= 
+    GeOp,        // >=
     EqOp,        // ==
     NeOp,        // !=
     AndOp,       // &&
     OrOp,        // ||
-    MulAssign,   // *=
-    DivAssign,   // /=
-    ModAssign,   // %=
-    AddAssign,   // +=
-    SubAssign,   // -=
-    LeftAssign,  // <<=
-    RightAssign, // >>=
-    AndAssign,   // &=
-    XorAssign,   // ^=
-    OrAssign,    // |=
     EOF,
-    /* // TODO: this should be done when we found this is a typedef name,
-    //       typedef LL int, then LL is typedef_name
-    TypedefName,
-    ELLIPSIS,                    // ...
-    EnumerationConstant(String), // TODO: add check
-    TYPEDEF,
-    EXTERN,
-    STATIC,
-    AUTO,
-    REGISTER,
-    INLINE,
-    CONST,
-    RESTRICT,
-    VOLATILE,
-    BOOL,
-    CHAR,
-    SHORT,
-    INT,
-    LONG,
-    SIGNED,
-    UNSIGNED,
-    FLOAT,
-    DOUBLE,
-    VOID,
-    COMPLEX,
-    IMAGINARY,
-    STRUCT,
-    UNION,
-    ENUM,
-    CASE,
-    DEFAULT, */
+
+    Module,
+    Function,
     IF,
     ELSE,
-    /* SWITCH,
-    WHILE,
-    DO,
-    FOR,
-    GOTO,
-    CONTINUE,
-    BREAK,
-    RETURN,
-    ALIGNAS,
-    ALIGNOF,
-    ATOMIC,
-    GENERIC,
-    NORETURN,
-    StaticAssert,
-    ThreadLocal, */
+    Let,
+    Each,
+    For,
+    IntersectionFor,
+    Assert,
+    Echo,
+    Include,
+    Use,
 }
 
-impl TokType {
-    #[allow(clippy::cognitive_complexity)]
-    pub fn lex(input: &str) -> Result<Vec<LexType>, String> {
-        let mut result = Vec::new();
+impl<'src> TokType<'src> {
+    pub fn lex(input: &'src str) -> Result<Vec<LexType<'src>>, LexError> {
+        Lexer::new(input).collect()
+    }
 
-        let mut line: usize = 1;
-        let mut collum: usize = 1;
-
-        let mut it = input.chars().peekable();
-
-        while let Some(&c) = it.peek() {
-            match c {
-                '"' => {    // FIXME: EOF
-                    collum += 1;
-                    let start = collum;
-                    let start_line = line;
-                    it.next();
-                    let mut s = "".to_string();
-                    while let Some(&c) = it.peek() {
-                        if c == '"' {
-                            collum += 1;;
-                            it.next();
-                            break;
-                        }
-                        if c == '\n' {
-                            collum = 1;
-                            line += 1;
-                        }
-                        s.push(c);
-                        collum += 1;;
-                        it.next();
-                    }
-                    trace!("StringLiteral: {}", s);
-                    result.push(LexType::new(TokType::StringLiteral(s, "fixme".to_string()), start_line, start));
-                }
-                '\'' => {   // FIXME: EOF
-                    it.next();
-                    collum += 1;
-                    let start = collum;
-                    let start_line = line;
-                    let mut s = String::new();
-                    while let Some(&c) = it.peek() {
-                        if c == '\'' {
-                            it.next();
-                            collum += 1;;
-                            break;
-                        }
-                        if c == '\n' {
-                            collum = 1;
-                            line += 1;
-                        }
-                        s.push(c);
-                        it.next();
-                        collum += 1;
-                    }
-                    trace!("StringLiteral: {} at {}:{}", s, line, collum);
-                    result.push(LexType::new(TokType::StringLiteral(s, "fixme".to_string()), start_line, start));
-                }
-                '0'..='9' => {
-                    it.next();
-                    collum += 1;
-                    let start = collum;
-                    let mut number = c
-                        .to_string()
-                        .parse::<i64>()
-                        .expect("The caller should have passed a digit.");
-                    
-                    while let Some(Ok(digit)) = it.peek().map(|c| c.to_string().parse::<i64>()) {
-                        number = number * 10 + digit;
-                        it.next();
-                        collum += 1;;
-                    }
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '.' => {
-                                it.next();
-                                collum += 1;;
-                                let mut number = number as f64;
-                                let mut i = 10;
-                                while let Some(Ok(digit)) = it.peek().map(|c| c.to_string().parse::<i64>()) {
-                                    //println!("divider is {}, so number is {}, additor is {}", i, number, (digit as f64 / f64::from(i)));
-                                    number += digit as f64 / f64::from(i);
-                                    //println!("divider is {}, so number is {}, additor is {}", i, number, (digit as f64 / f64::from(i)));
-                                    i *= 10;
-                                    it.next();
-                                    collum += 1;;
-                                }
-                                warn!("FConstants are still experimental: got floating constant {}", number);
-                                result.push(LexType::new(TokType::FConstant(number), line, start));
-                            }
-                            _ => {
-                                trace!("IConstant {}", number);
-                                result.push(LexType::new(TokType::IConstant(number), line, start));
-                            }
-                        }
-                        _ => {
-                            trace!("IConstant {}", number);
-                            result.push(LexType::new(TokType::IConstant(number), line, start));
-                        }
-                    }
-                }
-                'a'..='z' | 'A'..='Z' | '$' | '_' => {
-                    it.next();
-                    let start = collum;
-                    collum += 1;
-                    let mut s = String::new();
-                    s.push(c);
-                    while let Some(&tmp) = it.peek() {
-                        match tmp {
-                            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
-                                s.push(tmp);
-                                it.next();
-                                collum += 1;;
-                            }
-                            _ => {
-                                break;
-                            }
-                        }
-                    }
-                    trace!("got identifier {}", s);
-                    match s.as_ref() {
-                        "module" => result.push(LexType::new(TokType::Module, line, start)),
-                        "if" => result.push(LexType::new(TokType::IF, line, start)),
-                        "else" => result.push(LexType::new(TokType::ELSE, line, start)),
-                        _ => result.push(LexType::new(TokType::IDENTIFIER(s), line, start)),
-                    }
-                }
-                '(' => {
-                    it.next();
-                    collum += 1;
-                    result.push(LexType::new(TokType::LParen, line, collum));
-                }
-                ')' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::RParen, line, collum));
-                }
-                '{' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::LBrace, line, collum));
-                }
-                '}' => {
-                    it.next();
-                    collum += 1;
-                    result.push(LexType::new(TokType::RBrace, line, collum));
-                }
-                '[' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::LBracket, line, collum));
-                }
-                ']' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::RBracket, line, collum));
-                }
-                ';' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Semicolon, line, collum));
-                }
-                '=' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::EqOp, line, collum));
-                            }
-                            _ => { result.push(LexType::new(TokType::Assign, line, collum)); },
-                        },
-                        _ => { result.push(LexType::new(TokType::Assign, line, collum)); },
-                    }
+    /// Lexes the whole input even if it contains errors, emitting an `Error`
+    /// token for every bad piece of input and returning all errors found.
+    pub fn lex_recovering(input: &'src str) -> (Vec<LexType<'src>>, Vec<LexError>) {
+        Lexer::new(input).lex_recovering()
+    }
+
+    /// Lexes the input including comment and whitespace trivia, so that the
+    /// token texts concatenate to exactly the input.
+    pub fn lex_lossless(input: &'src str) -> Result<Vec<LexType<'src>>, LexError> {
+        Lexer::new(input).with_trivia().collect()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokType::Comment(_) | TokType::BlockComment(_) | TokType::Whitespace | TokType::Newline
+        )
+    }
+
+    pub fn into_owned(self) -> TokType<'static> {
+        match self {
+            TokType::IDENTIFIER(s) => TokType::IDENTIFIER(Cow::Owned(s.into_owned())),
+            TokType::StringLiteral(raw, value) => {
+                TokType::StringLiteral(Cow::Owned(raw.into_owned()), Cow::Owned(value.into_owned()))
+            }
+            TokType::Comment(s) => TokType::Comment(Cow::Owned(s.into_owned())),
+            TokType::BlockComment(s) => TokType::BlockComment(Cow::Owned(s.into_owned())),
+            TokType::Whitespace => TokType::Whitespace,
+            TokType::Newline => TokType::Newline,
+            TokType::IncludePath(s) => TokType::IncludePath(Cow::Owned(s.into_owned())),
+            TokType::UsePath(s) => TokType::UsePath(Cow::Owned(s.into_owned())),
+            TokType::LBrace => TokType::LBrace,
+            TokType::RBrace => TokType::RBrace,
+            TokType::LParen => TokType::LParen,
+            TokType::RParen => TokType::RParen,
+            TokType::LBracket => TokType::LBracket,
+            TokType::RBracket => TokType::RBracket,
+            TokType::Semicolon => TokType::Semicolon,
+            TokType::Assign => TokType::Assign,
+            TokType::Lt => TokType::Lt,
+            TokType::Gt => TokType::Gt,
+            TokType::Minus => TokType::Minus,
+            TokType::Tilde => TokType::Tilde,
+            TokType::Exclamation => TokType::Exclamation,
+            TokType::Plus => TokType::Plus,
+            TokType::Multi => TokType::Multi,
+            TokType::Splash => TokType::Splash,
+            TokType::Colon => TokType::Colon,
+            TokType::QuestionMark => TokType::QuestionMark,
+            TokType::Comma => TokType::Comma,
+            TokType::Dot => TokType::Dot,
+            TokType::SingleAnd => TokType::SingleAnd,
+            TokType::InclusiveOr => TokType::InclusiveOr,
+            TokType::ExclusiveOr => TokType::ExclusiveOr,
+            TokType::Mod => TokType::Mod,
+            TokType::IConstant(i) => TokType::IConstant(i),
+            TokType::FConstant(f) => TokType::FConstant(f),
+            TokType::Module => TokType::Module,
+            TokType::Error => TokType::Error,
+            TokType::RootModifier => TokType::RootModifier,
+            TokType::DisableModifier => TokType::DisableModifier,
+            TokType::BackgroundModifier => TokType::BackgroundModifier,
+            TokType::DebugModifier => TokType::DebugModifier,
+            TokType::LeftOp => TokType::LeftOp,
+            TokType::RightOp => TokType::RightOp,
+            TokType::LeOp => TokType::LeOp,
+            TokType::GeOp => TokType::GeOp,
+            TokType::EqOp => TokType::EqOp,
+            TokType::NeOp => TokType::NeOp,
+            TokType::AndOp => TokType::AndOp,
+            TokType::OrOp => TokType::OrOp,
+            TokType::EOF => TokType::EOF,
+            TokType::Bool(b) => TokType::Bool(b),
+            TokType::Undef => TokType::Undef,
+            TokType::Function => TokType::Function,
+            TokType::IF => TokType::IF,
+            TokType::ELSE => TokType::ELSE,
+            TokType::Let => TokType::Let,
+            TokType::Each => TokType::Each,
+            TokType::For => TokType::For,
+            TokType::IntersectionFor => TokType::IntersectionFor,
+            TokType::Assert => TokType::Assert,
+            TokType::Echo => TokType::Echo,
+            TokType::Include => TokType::Include,
+            TokType::Use => TokType::Use,
+        }
+    }
+}
+
+/// Identifiers start with a letter, `_` or `$`. Besides ASCII, letters of any
+/// script are accepted, e.g. `höhe`.
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c == '$' || c.is_alphabetic()
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+const IDENT: u8 = 1;
+const DIGIT: u8 = 2;
+const BLANK: u8 = 4;
+
+/// Classes of the ASCII bytes, used by the scanning loops instead of
+/// decoding chars. Bytes of multi-byte chars have no class.
+static BYTE_CLASSES: [u8; 256] = byte_classes();
+
+const fn byte_classes() -> [u8; 256] {
+    let mut classes = [0; 256];
+    let mut b = 0;
+    while b < 128 {
+        let c = b as u8;
+        if c.is_ascii_alphabetic() || c == b'_' {
+            classes[b] = IDENT;
+        } else if c.is_ascii_digit() {
+            classes[b] = IDENT | DIGIT;
+        } else if c == b' ' || c == b'\t' {
+            classes[b] = BLANK;
+        }
+        b += 1;
+    }
+    classes
+}
+
+/// Lazily lexes `src`, yielding tokens up to and including `EOF`.
+///
+/// After an error the lexer continues behind the offending input, so
+/// iterating to the end reports every lexical error of the source.
+#[derive(Debug, Clone)]
+pub struct Lexer<'src> {
+    src: &'src str,
+    bytes: &'src [u8],
+    pos: usize,
+    token_start: usize,
+    peeked: Option<Option<Result<LexType<'src>, LexError>>>,
+    done: bool,
+    trivia: bool,
+    modifiers: ModifierClassifier,
+    file: FileId,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(src: &'src str) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+            token_start: 0,
+            peeked: None,
+            done: false,
+            trivia: false,
+            modifiers: ModifierClassifier::new(),
+            file: FileId::default(),
+        }
+    }
+
+    /// Starts lexing at byte offset `pos`, which must be a token boundary,
+    /// with the modifier context of the token there.
+    fn starting_at(mut self, pos: usize, modifiers: ModifierClassifier) -> Self {
+        self.pos = pos;
+        self.token_start = pos;
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Also yield `Comment`, `BlockComment`, `Whitespace` and `Newline` tokens.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    /// Sets the file the spans of tokens and errors refer to.
+    pub fn with_file(mut self, file: FileId) -> Self {
+        self.file = file;
+        self
+    }
+
+    pub fn source(&self) -> &'src str {
+        self.src
+    }
+
+    /// Collects all remaining tokens, replacing the input consumed by each
+    /// error with an `Error` token.
+    pub fn lex_recovering(mut self) -> (Vec<LexType<'src>>, Vec<LexError>) {
+        let mut result = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(tok) = self.next() {
+            match tok {
+                Ok(tok) => result.push(tok),
+                Err(err) => {
+                    // the error may only point at part of the consumed input,
+                    // e.g. a bad escape inside a string
+                    result.push(LexType::new(TokType::Error, self.span(self.token_start)));
+                    errors.push(err);
                 }
-                '<' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::LeOp, line, collum));
-                            }
-                            '<' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::LeftOp, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Lt, line, collum));
-                            }
-                        },
-                        _ => {
-                            result.push(LexType::new(TokType::Lt, line, collum));
-                        }
-                    }
+            }
+        }
+        (result, errors)
+    }
+
+    pub fn peek(&mut self) -> Option<&Result<LexType<'src>, LexError>> {
+        if self.peeked.is_none() {
+            let next = self.lex_next();
+            self.peeked = Some(next);
+        }
+        self.peeked.as_ref().and_then(Option::as_ref)
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek_byte() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_class(&mut self, class: u8) {
+        while let Some(&b) = self.bytes.get(self.pos) {
+            if BYTE_CLASSES[b as usize] & class == 0 {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn span(&self, start: usize) -> Span {
+        Span::new(start, self.pos).in_file(self.file)
+    }
+
+    fn lex_next(&mut self) -> Option<Result<LexType<'src>, LexError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let start = self.pos;
+            self.token_start = start;
+            let b = match self.peek_byte() {
+                Some(b) => b,
+                None => {
+                    self.done = true;
+                    return Some(Ok(LexType::new(TokType::EOF, self.span(start))));
                 }
-                '>' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::GeOp, line, collum));
-                            },
-                            '>' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::RightOp, line, collum));
-                            },
-                            _ => {
-                                result.push(LexType::new(TokType::Gt, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Gt, line, collum));
-                        }
+            };
+            self.pos += 1;
+            let token = match b {
+                b'"' | b'\'' => match self.lex_string(start, b) {
+                    Ok(token) => token,
+                    Err(err) => return Some(Err(err)),
+                },
+                b'0'..=b'9' | b'.' if b != b'.' || self.peek_byte().is_some_and(|b| b.is_ascii_digit()) => {
+                    match self.lex_number(start) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
                     }
                 }
-                '-' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '-' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::DecOp, line, collum));
-                            }
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::SubAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Minus, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Minus, line, collum));
+                b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'$' => match self.lex_identifier(start) {
+                    TokType::Include => match self.lex_path(TokType::Include, TokType::IncludePath) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    TokType::Use => match self.lex_path(TokType::Use, TokType::UsePath) {
+                        Ok(token) => token,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    token => token,
+                },
+                b'\n' => TokType::Newline,
+                b'\r' if self.eat(b'\n') => TokType::Newline,
+                b' ' | b'\t' | b'\r' => {
+                    loop {
+                        self.eat_class(BLANK);
+                        if self.peek_byte() != Some(b'\r') || self.bytes.get(self.pos + 1) == Some(&b'\n') {
+                            break;
                         }
+                        self.pos += 1;
                     }
+                    TokType::Whitespace
                 }
-                '~' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Tilde, line, collum));
-                }
-                '!' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::NeOp, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Exclamation, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Exclamation, line, collum));
-                        }
+                b'/' if self.eat(b'/') => {
+                    trace!("got comment");
+                    let mut end = memchr::memchr(b'\n', &self.bytes[self.pos..]).map_or(self.bytes.len(), |i| self.pos + i);
+                    if self.bytes[..end].ends_with(b"\r") {
+                        // keep the \r\n together as a Newline
+                        end -= 1;
                     }
+                    self.pos = end;
+                    TokType::Comment(Cow::Borrowed(&self.src[start + 2..end]))
                 }
-                '+' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '+' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::IncOp, line, collum));
-                            }
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::AddAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Plus, line, collum));
-                            }
+                b'/' if self.eat(b'*') => {
+                    trace!("got comment");
+                    match memchr::memmem::find(&self.bytes[self.pos..], b"*/") {
+                        Some(end) => {
+                            let text = &self.src[self.pos..self.pos + end];
+                            self.pos += end + 2;
+                            TokType::BlockComment(Cow::Borrowed(text))
                         }
-                        _ => {
-                            result.push(LexType::new(TokType::Plus, line, collum));
+                        None => {
+                            self.pos = self.src.len();
+                            let span = Span::new(start, start + 2).in_file(self.file);
+                            return Some(Err(LexError::UnterminatedBlockComment { span }));
                         }
                     }
                 }
-                '*' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::MulAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Multi, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Multi, line, collum));
-                        }
+                b'(' => TokType::LParen,
+                b')' => TokType::RParen,
+                b'{' => TokType::LBrace,
+                b'}' => TokType::RBrace,
+                b'[' => TokType::LBracket,
+                b']' => TokType::RBracket,
+                b';' => TokType::Semicolon,
+                b'~' => TokType::Tilde,
+                b'?' => TokType::QuestionMark,
+                b':' => TokType::Colon,
+                b',' => TokType::Comma,
+                b'#' => TokType::DebugModifier,
+                b'.' => TokType::Dot,
+                b'=' if self.eat(b'=') => TokType::EqOp,
+                b'=' => TokType::Assign,
+                b'<' if self.eat(b'=') => TokType::LeOp,
+                b'<' if self.eat(b'<') => TokType::LeftOp,
+                b'<' => TokType::Lt,
+                b'>' if self.eat(b'=') => TokType::GeOp,
+                b'>' if self.eat(b'>') => TokType::RightOp,
+                b'>' => TokType::Gt,
+                b'-' => TokType::Minus,
+                b'!' if self.eat(b'=') => TokType::NeOp,
+                b'!' => TokType::Exclamation,
+                b'+' => TokType::Plus,
+                b'*' => TokType::Multi,
+                b'/' => TokType::Splash,
+                b'%' => TokType::Mod,
+                b'&' if self.eat(b'&') => TokType::AndOp,
+                b'&' => TokType::SingleAnd,
+                b'|' if self.eat(b'|') => TokType::OrOp,
+                b'|' => TokType::InclusiveOr,
+                b'^' => TokType::ExclusiveOr,
+                _ => {
+                    // slow path for everything outside of ASCII
+                    self.pos = start;
+                    let c = self.bump().expect("not at the end of the input");
+                    if is_identifier_start(c) {
+                        self.lex_identifier(start)
+                    } else {
+                        let span = self.span(start);
+                        debug!("unexpected character {:?} at {:?}", c, span);
+                        return Some(Err(LexError::UnexpectedChar { ch: c, span }));
                     }
                 }
-                '%' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::ModAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Mod, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Mod, line, collum));
-                        }
-                    }
+            };
+            if token.is_trivia() && !self.trivia {
+                continue;
+            }
+            let token = self.modifiers.classify(token);
+            return Some(Ok(LexType::new(token, self.span(start))));
+        }
+    }
+
+    /// Lexes a string literal, keeping the raw source text between the quotes
+    /// and decoding its escape sequences into the value.
+    fn lex_string(&mut self, start: usize, quote: u8) -> Result<TokType<'src>, LexError> {
+        let src = self.src;
+        let content = start + 1;
+        // only allocated once the first escape sequence is seen
+        let mut value: Option<String> = None;
+        let mut error = None;
+        let end = loop {
+            let segment = self.pos;
+            let found = match memchr::memchr2(quote, b'\\', &self.bytes[segment..]) {
+                Some(i) => segment + i,
+                None => {
+                    self.pos = self.bytes.len();
+                    let span = Span::new(start, content).in_file(self.file);
+                    return Err(LexError::UnterminatedString { span });
                 }
-                '/' => {
-                    it.next();
-                    collum += 1;
-                    let start = collum;
-                    let _start_line = line;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::DivAssign, line, start));
-                            }
-                            '/' => {
-                                trace!("got comment");
-                                it.next();
-                                collum += 1;;
-                                while let Some(&c) = it.peek() {
-                                    if c == '\n' {
-                                        it.next();
-                                        collum = 1;
-                                        line += 1;
-                                        break;
-                                    }
-                                    it.next();
-                                    collum += 1;;
-                                }
-                            }
-                            '*' => {
-                                trace!("got comment");
-                                it.next();
-                                collum += 1;;
-                                while let Some(&c) = it.peek() {    // FIXME: not ending?
-                                    if c == '*' {
-                                        it.next();
-                                        collum += 1;;
-                                        if let Some(&c) = it.peek() {
-                                            if c == '/' {
-                                                it.next();
-                                                collum += 1;;
-                                                break;
-                                            }
-                                            it.next();
-                                            collum += 1;;
-                                        }
-                                    }
-                                    if c == '\n' {
-                                        collum = 1;
-                                        line += 1;
-                                    }
-                                    it.next();
-                                    collum += 1;;
-                                }
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::Splash, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::Splash, line, collum));
-                        }
-                    }
+            };
+            self.pos = found + 1;
+            if self.bytes[found] == quote {
+                if let Some(value) = &mut value {
+                    value.push_str(&src[segment..found]);
                 }
-                '&' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '&' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::AndOp, line, collum));
-                            }
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::AndAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::SingleAnd, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::SingleAnd, line, collum));
-                        }
-                    }
+                break found;
+            }
+            let value = value.get_or_insert_with(String::new);
+            value.push_str(if value.is_empty() { &src[content..found] } else { &src[segment..found] });
+            match self.lex_escape(found) {
+                Ok(c) => value.push(c),
+                Err(err) => {
+                    error.get_or_insert(err);
                 }
-                '|' => {
-                    it.next();
-                    collum += 1;;
-                    match it.peek() {
-                        Some(tmp) => match tmp {
-                            '|' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::OrOp, line, collum));
-                            }
-                            '=' => {
-                                it.next();
-                                collum += 1;;
-                                result.push(LexType::new(TokType::OrAssign, line, collum));
-                            }
-                            _ => {
-                                result.push(LexType::new(TokType::InclusiveOr, line, collum));
-                            }
-                        }
-                        _ => {
-                            result.push(LexType::new(TokType::InclusiveOr, line, collum));
-                        }
-                    }
+            }
+        };
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let raw = &src[content..end];
+        trace!("StringLiteral: {}", raw);
+        let value = match value {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(raw),
+        };
+        Ok(TokType::StringLiteral(Cow::Borrowed(raw), value))
+    }
+
+    /// Decodes the escape sequence whose backslash is at `start`: `\t`, `\n`,
+    /// `\r`, `\\`, `\"`, `\'`, `\x7F`, `\u263A` or `\U01F600`.
+    fn lex_escape(&mut self, start: usize) -> Result<char, LexError> {
+        let (digits, max) = match self.bump() {
+            Some('t') => return Ok('\t'),
+            Some('n') => return Ok('\n'),
+            Some('r') => return Ok('\r'),
+            Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => return Ok(c),
+            Some('x') => (2, 0x7f),
+            Some('u') => (4, 0xffff),
+            Some('U') => (6, 0x10_ffff),
+            _ => return Err(LexError::InvalidEscape { span: self.span(start) }),
+        };
+        let hex = self.pos;
+        while self.pos - hex < digits && self.peek_byte().is_some_and(|b| b.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let code = u32::from_str_radix(&self.src[hex..self.pos], 16).ok();
+        match code.filter(|&code| code > 0 && code <= max).and_then(std::char::from_u32) {
+            Some(c) if self.pos - hex == digits => Ok(c),
+            _ => Err(LexError::InvalidEscape { span: self.span(start) }),
+        }
+    }
+
+    /// Lexes the OpenSCAD number forms `1`, `1.`, `1.5`, `.5` and any of
+    /// them followed by an exponent like `e-3` or `E+4`.
+    fn lex_number(&mut self, start: usize) -> Result<TokType<'src>, LexError> {
+        self.pos = start;
+        // integers are accumulated while scanning, `None` on overflow
+        let mut integer = Some(0i64);
+        while let Some(&b) = self.bytes.get(self.pos) {
+            if !b.is_ascii_digit() {
+                break;
+            }
+            integer = integer
+                .and_then(|n| n.checked_mul(10))
+                .and_then(|n| n.checked_add(i64::from(b - b'0')));
+            self.pos += 1;
+        }
+        let mut float = false;
+        if self.eat(b'.') {
+            float = true;
+            self.eat_class(DIGIT);
+        }
+        if let Some(b'e') | Some(b'E') = self.peek_byte() {
+            float = true;
+            self.pos += 1;
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            let digits = self.pos;
+            self.eat_class(DIGIT);
+            if self.pos == digits {
+                return Err(LexError::InvalidNumber { span: self.span(start) });
+            }
+        }
+
+        let s = &self.src[start..self.pos];
+        if !float {
+            if let Some(number) = integer {
+                trace!("IConstant {}", number);
+                return Ok(TokType::IConstant(number));
+            }
+            debug!("integer constant {} overflows i64, using a float", s);
+        }
+        match s.parse::<f64>() {
+            Ok(number) => {
+                trace!("FConstant {}", number);
+                Ok(TokType::FConstant(number))
+            }
+            Err(_) => Err(LexError::InvalidNumber { span: self.span(start) }),
+        }
+    }
+
+    fn lex_identifier(&mut self, start: usize) -> TokType<'src> {
+        loop {
+            self.eat_class(IDENT);
+            match self.peek_byte() {
+                Some(b) if b >= 0x80 => match self.peek_char() {
+                    Some(c) if is_identifier_continue(c) => self.pos += c.len_utf8(),
+                    _ => break,
                 },
-                '?' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::QuestionMark, line, collum));
-                }
-                ':' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Colon, line, collum));
-                }
-                ',' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Comma, line, collum));
-                }
-                '#' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Highlight, line, collum));
-                }
-                '.' => {
-                    it.next();
-                    collum += 1;;
-                    result.push(LexType::new(TokType::Dot, line, collum));
-                }
-                ' ' | '\t' | '\r' => {
-                    //skip
-                    it.next();
-                    collum += 1;;
-                }
-                '\n' => {
-                    it.next();
-                    collum = 1;
-                    line += 1;
-                }
-                _ => {
-                    //error!("unexpected Character {}", c);
-                    //it.next();
-                    return Err(format!("unexpected Character {} at {}:{}", c, line, collum));
-                }
+                _ => break,
             }
         }
-        result.push(LexType::new(TokType::EOF, line, collum));
-        Ok(result)
+        let s = &self.src[start..self.pos];
+        trace!("got identifier {}", s);
+        match s {
+            "module" => TokType::Module,
+            "function" => TokType::Function,
+            "if" => TokType::IF,
+            "else" => TokType::ELSE,
+            "let" => TokType::Let,
+            "each" => TokType::Each,
+            "for" => TokType::For,
+            "intersection_for" => TokType::IntersectionFor,
+            "assert" => TokType::Assert,
+            "echo" => TokType::Echo,
+            "include" => TokType::Include,
+            "use" => TokType::Use,
+            "true" => TokType::Bool(true),
+            "false" => TokType::Bool(false),
+            "undef" => TokType::Undef,
+            _ => TokType::IDENTIFIER(Cow::Borrowed(s)),
+        }
+    }
+
+    /// Lexes the `<path>` following an `include` or `use` keyword into a
+    /// single token together with the keyword. Without a `<` the keyword
+    /// stays a keyword token.
+    fn lex_path(
+        &mut self,
+        keyword: TokType<'src>,
+        path: fn(Cow<'src, str>) -> TokType<'src>,
+    ) -> Result<TokType<'src>, LexError> {
+        let keyword_end = self.pos;
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') = self.peek_byte() {
+            self.pos += 1;
+        }
+        let open = self.pos;
+        if !self.eat(b'<') {
+            self.pos = keyword_end;
+            return Ok(keyword);
+        }
+        while let Some(b) = self.peek_byte() {
+            if b == b'>' || b == b'\t' || b == b'\r' || b == b'\n' {
+                break;
+            }
+            self.pos += 1;
+        }
+        let end = self.pos;
+        if !self.eat(b'>') {
+            return Err(LexError::UnterminatedPath { span: Span::new(open, open + 1).in_file(self.file) });
+        }
+        let s = &self.src[open + 1..end];
+        trace!("got path {}", s);
+        Ok(path(Cow::Borrowed(s)))
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<LexType<'src>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(next) => next,
+            None => self.lex_next(),
+        }
     }
 }
@@ -0,0 +1,87 @@
+use super::TokType;
+
+/// Tells the modifier characters `!`, `*` and `%` in front of a module
+/// instantiation apart from the operators spelled the same way.
+///
+/// A character is a modifier at statement position: at the start of the file
+/// or behind `;`, `{`, `}`, `else`, an include, another modifier or the `)`
+/// closing the arguments of a module or control statement, as long as the
+/// token is not part of an expression like `x = (a) * b;`. `#` is always a
+/// modifier, so it does not need the context.
+///
+/// The state is small and `Copy`, so the incremental lexer can store it per
+/// token and resume classification anywhere.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct ModifierClassifier {
+    depth: usize,
+    expression: bool,
+    statement_start: bool,
+}
+
+impl ModifierClassifier {
+    pub(crate) fn new() -> Self {
+        Self {
+            depth: 0,
+            expression: false,
+            statement_start: true,
+        }
+    }
+
+    /// Classifies the next significant token and advances the state past it.
+    pub(crate) fn classify<'src>(&mut self, token: TokType<'src>) -> TokType<'src> {
+        let token = match token {
+            TokType::Exclamation if self.statement_start => TokType::RootModifier,
+            TokType::Multi if self.statement_start => TokType::DisableModifier,
+            TokType::Mod if self.statement_start => TokType::BackgroundModifier,
+            token => token,
+        };
+        match token {
+            ref token if token.is_trivia() => {}
+            // the lexer does not classify its errors either
+            TokType::Error => {}
+            TokType::LParen | TokType::LBracket => {
+                self.depth += 1;
+                self.statement_start = false;
+            }
+            TokType::RParen | TokType::RBracket => {
+                self.depth = self.depth.saturating_sub(1);
+                self.statement_start = self.depth == 0 && !self.expression;
+            }
+            // braces only appear around statements, which also recovers from
+            // unbalanced brackets
+            TokType::LBrace | TokType::RBrace => {
+                self.depth = 0;
+                self.expression = false;
+                self.statement_start = true;
+            }
+            TokType::Semicolon if self.depth == 0 => {
+                self.expression = false;
+                self.statement_start = true;
+            }
+            TokType::Assign if self.depth == 0 => {
+                self.expression = true;
+                self.statement_start = false;
+            }
+            TokType::ELSE
+            | TokType::IncludePath(_)
+            | TokType::UsePath(_)
+            | TokType::RootModifier
+            | TokType::DisableModifier
+            | TokType::BackgroundModifier
+            | TokType::DebugModifier => self.statement_start = self.depth == 0 && !self.expression,
+            _ => self.statement_start = false,
+        }
+        token
+    }
+
+    /// Classifies a token that was classified before with a different state.
+    pub(crate) fn reclassify<'src>(&mut self, token: TokType<'src>) -> TokType<'src> {
+        let token = match token {
+            TokType::RootModifier => TokType::Exclamation,
+            TokType::DisableModifier => TokType::Multi,
+            TokType::BackgroundModifier => TokType::Mod,
+            token => token,
+        };
+        self.classify(token)
+    }
+}
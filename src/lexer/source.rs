@@ -0,0 +1,135 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{FileId, LexError, LexType, Lexer, SourceMap, Span};
+
+/// A loaded source file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceFile {
+    path: PathBuf,
+    map: SourceMap,
+}
+
+impl SourceFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        self.map.text()
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.map
+    }
+}
+
+/// Resolved position of a span start, displayed as `path:line:col`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Location<'a> {
+    pub path: &'a Path,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path.display(), self.line, self.column)
+    }
+}
+
+/// Owns the files of a program, the main file and everything reached through
+/// `include` and `use`, and resolves spans to their file and position.
+#[derive(Debug, Default, Clone)]
+pub struct SourceDb {
+    files: Vec<SourceFile>,
+}
+
+impl SourceDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given text. A file already added under the same
+    /// path keeps its id and gets the new text.
+    pub fn add(&mut self, path: impl Into<PathBuf>, text: &str) -> FileId {
+        let path = path.into();
+        let map = SourceMap::new(text);
+        match self.file_id(&path) {
+            Some(id) => {
+                self.files[id.index()].map = map;
+                id
+            }
+            None => {
+                self.files.push(SourceFile { path, map });
+                FileId((self.files.len() - 1) as u32)
+            }
+        }
+    }
+
+    /// Reads the file at `path` unless it was loaded before.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<FileId> {
+        let path = path.as_ref();
+        if let Some(id) = self.file_id(path) {
+            return Ok(id);
+        }
+        info!("loading {}", path.display());
+        let text = fs::read_to_string(path)?;
+        Ok(self.add(path, &text))
+    }
+
+    pub fn file_id(&self, path: &Path) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|file| file.path == path)
+            .map(|i| FileId(i as u32))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.iter().enumerate().map(|(i, file)| (FileId(i as u32), file))
+    }
+
+    /// Panics if `id` is not a file of this database.
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+
+    pub fn path(&self, id: FileId) -> &Path {
+        self.file(id).path()
+    }
+
+    pub fn text(&self, id: FileId) -> &str {
+        self.file(id).text()
+    }
+
+    /// The text covered by `span`.
+    pub fn snippet(&self, span: Span) -> &str {
+        &self.text(span.file)[span.range()]
+    }
+
+    /// Location of the start of `span`, the column counted in chars.
+    pub fn location(&self, span: Span) -> Location<'_> {
+        let file = self.file(span.file);
+        let (line, column) = file.map.line_col(span.start);
+        Location {
+            path: &file.path,
+            line,
+            column,
+        }
+    }
+
+    /// Lexes a file like `TokType::lex_recovering`, with spans in that file.
+    pub fn lex(&self, id: FileId) -> (Vec<LexType<'_>>, Vec<LexError>) {
+        Lexer::new(self.text(id)).with_file(id).lex_recovering()
+    }
+}
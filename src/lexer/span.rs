@@ -0,0 +1,192 @@
+use std::ops::Range;
+
+/// Identifies a file of a `SourceDb`. `FileId::default()` is the first file
+/// added, which is also the file of spans created without one.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileId(pub(crate) u32);
+
+impl FileId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Byte range `start..end` into the source of `file`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file: FileId,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        debug_assert!(start <= end, "span start {} after end {}", start, end);
+        Self {
+            start,
+            end,
+            file: FileId::default(),
+        }
+    }
+
+    pub fn in_file(self, file: FileId) -> Self {
+        Self { file, ..self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The smallest span covering both spans, which must be in the same file.
+    pub fn to(self, other: Span) -> Self {
+        debug_assert_eq!(self.file, other.file, "spans in different files");
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            file: self.file,
+        }
+    }
+
+    /// Moves the span by `delta` bytes, for text inserted or removed before it.
+    pub(crate) fn shifted(self, delta: isize) -> Self {
+        Self {
+            start: (self.start as isize + delta) as usize,
+            end: (self.end as isize + delta) as usize,
+            ..self
+        }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+/// Maps byte offsets of a source text to 1-based line/column pairs.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceMap {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            text.bytes()
+                .enumerate()
+                .filter(|&(_, b)| b == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        Self {
+            text: text.to_string(),
+            line_starts,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// 0-based index of the line containing `offset`.
+    pub fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// Byte range of the 0-based line `line`, without its line terminator.
+    pub fn line_span(&self, line: usize) -> Span {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |&next| next - 1);
+        let end = if self.text[start..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+        Span::new(start, end)
+    }
+
+    /// 1-based line and column of `offset`, the column counted in chars.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        self.line_col_with(offset, |_| 1)
+    }
+
+    /// 1-based line and column of `offset`, the column counted in bytes.
+    pub fn line_col_utf8(&self, offset: usize) -> (usize, usize) {
+        self.line_col_with(offset, char::len_utf8)
+    }
+
+    /// 1-based line and column of `offset`, the column counted in UTF-16 code
+    /// units as used by LSP and VS Code.
+    pub fn line_col_utf16(&self, offset: usize) -> (usize, usize) {
+        self.line_col_with(offset, char::len_utf16)
+    }
+
+    /// Byte offset of the 1-based line and char column, `None` if the position
+    /// is outside of the text.
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        self.offset_with(line, column, |_| 1)
+    }
+
+    /// Byte offset of the 1-based line and byte column.
+    pub fn offset_utf8(&self, line: usize, column: usize) -> Option<usize> {
+        self.offset_with(line, column, char::len_utf8)
+    }
+
+    /// Byte offset of the 1-based line and UTF-16 column. Columns pointing into
+    /// the middle of a surrogate pair return `None`.
+    pub fn offset_utf16(&self, line: usize, column: usize) -> Option<usize> {
+        self.offset_with(line, column, char::len_utf16)
+    }
+
+    fn line_col_with(&self, offset: usize, width: fn(char) -> usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_index(offset);
+        let start = self.line_starts[line];
+        let column: usize = self.text[start..offset].chars().map(width).sum();
+        (line + 1, column + 1)
+    }
+
+    fn offset_with(&self, line: usize, column: usize, width: fn(char) -> usize) -> Option<usize> {
+        if line == 0 || line > self.line_count() || column == 0 {
+            return None;
+        }
+        let span = self.line_span(line - 1);
+        let mut units = 0;
+        for (i, c) in self.text[span.range()].char_indices() {
+            if units == column - 1 {
+                return Some(span.start + i);
+            }
+            if units > column - 1 {
+                return None;
+            }
+            units += width(c);
+        }
+        if units == column - 1 {
+            Some(span.end)
+        } else {
+            None
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use std::borrow::Cow;
 
-use super::{TokType, LexType};
+use std::path::Path;
+
+use super::baseline::CharLexer;
+use super::{FileId, IncrementalLexer, TokType, LexType, LexError, Lexer, OwnedLexType, SourceDb, SourceMap, Span, TextEdit};
 
 #[test]
 fn lex() {
@@ -11,18 +15,23 @@ module Lex(size=20, $fn=100) {
 }
 echo(version=version());
 "#;
+    let map = SourceMap::new(scad);
     let scad = TokType::lex(scad).unwrap();
 
     assert_eq!(scad.len(), 35);
-    assert_eq!(scad[0], LexType::new(TokType::IDENTIFIER("Lex".to_string()), 2, 1));
-    assert_eq!(scad[1], LexType::new(TokType::LParen, 2, 5));
-    assert_eq!(scad[2], LexType::new(TokType::IConstant(20), 2, 6));
-    assert_eq!(scad[3], LexType::new(TokType::RParen, 2, 8));
-    assert_eq!(scad[4], LexType::new(TokType::Semicolon, 2, 9));
+    assert_eq!(scad[0], LexType::new(TokType::IDENTIFIER("Lex".into()), Span::new(26, 29)));
+    assert_eq!(scad[1], LexType::new(TokType::LParen, Span::new(29, 30)));
+    assert_eq!(scad[2], LexType::new(TokType::IConstant(20), Span::new(30, 32)));
+    assert_eq!(scad[3], LexType::new(TokType::RParen, Span::new(32, 33)));
+    assert_eq!(scad[4], LexType::new(TokType::Semicolon, Span::new(33, 34)));
 
-    assert_eq!(scad[5], LexType::new(TokType::Module, 4, 1));
+    assert_eq!(scad[5], LexType::new(TokType::Module, Span::new(36, 42)));
+    assert_eq!(map.line_col(scad[5].span.start), (4, 1));
+    assert_eq!(map.line_col(scad[1].span.start), (2, 4));
+    assert_eq!(map.line_col(scad[2].span.end), (2, 7));
 
-    assert_eq!(scad[34], LexType::new(TokType::EOF, 8, 1));
+    assert_eq!(scad[34], LexType::new(TokType::EOF, Span::new(114, 114)));
+    assert_eq!(map.line_col(scad[34].span.start), (8, 1));
 }
 
 #[test]
@@ -35,7 +44,474 @@ module Lex(size=20, §fn=100) {
 }
 echo(version=version());
 "#;
-    let scad = TokType::lex(scad);
+    let map = SourceMap::new(scad);
+    let err = TokType::lex(scad).unwrap_err();
+
+    assert_eq!(err, LexError::UnexpectedChar { ch: '§', span: Span::new(56, 58) });
+    assert_eq!(map.line_col(err.span().start), (4, 21));
+    assert_eq!(err.to_string(), "unexpected character `§`");
+}
+
+#[test]
+fn lex_recovering() {
+    let scad = "a = 1 § 2;\nb = °;\n";
+    let (tokens, errors) = TokType::lex_recovering(scad);
+
+    assert_eq!(
+        errors,
+        vec![
+            LexError::UnexpectedChar { ch: '§', span: Span::new(6, 8) },
+            LexError::UnexpectedChar { ch: '°', span: Span::new(16, 18) },
+        ]
+    );
+    assert_eq!(tokens.len(), 11);
+    assert_eq!(tokens[3], LexType::new(TokType::Error, Span::new(6, 8)));
+    assert_eq!(tokens[4], LexType::new(TokType::IConstant(2), Span::new(9, 10)));
+    assert_eq!(tokens[8], LexType::new(TokType::Error, Span::new(16, 18)));
+    assert_eq!(tokens[10].token, TokType::EOF);
+}
+
+#[test]
+fn lexer_peek() {
+    let mut lexer = Lexer::new("a §;");
+
+    assert_eq!(lexer.peek(), Some(&Ok(LexType::new(TokType::IDENTIFIER("a".into()), Span::new(0, 1)))));
+    assert_eq!(lexer.next(), Some(Ok(LexType::new(TokType::IDENTIFIER("a".into()), Span::new(0, 1)))));
+    assert_eq!(lexer.next(), Some(Err(LexError::UnexpectedChar { ch: '§', span: Span::new(2, 4) })));
+    assert_eq!(lexer.next(), Some(Ok(LexType::new(TokType::Semicolon, Span::new(4, 5)))));
+    assert_eq!(lexer.peek(), Some(&Ok(LexType::new(TokType::EOF, Span::new(5, 5)))));
+    assert_eq!(lexer.next(), Some(Ok(LexType::new(TokType::EOF, Span::new(5, 5)))));
+    assert_eq!(lexer.peek(), None);
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn lex_borrowed() {
+    let scad = "name = \"text\";";
+    let tokens = TokType::lex(scad).unwrap();
+
+    match (&tokens[0].token, &tokens[2].token) {
+        (TokType::IDENTIFIER(Cow::Borrowed(name)), TokType::StringLiteral(Cow::Borrowed(text), _)) => {
+            assert_eq!(*name, "name");
+            assert_eq!(*text, "text");
+        }
+        other => panic!("expected borrowed tokens, got {:?}", other),
+    }
+
+    let owned: Vec<OwnedLexType> = tokens.into_iter().map(LexType::into_owned).collect();
+    assert_eq!(owned[0], LexType::new(TokType::IDENTIFIER("name".into()), Span::new(0, 4)));
+}
+
+#[test]
+fn lex_numbers() {
+    let numbers = "0 42 1.5 10. .5 1e-3 2.5E+4 1.e2 0.1 3.14159265358979323846 9223372036854775807 9223372036854775808";
+    let tokens: Vec<TokType> = TokType::lex(numbers).unwrap().into_iter().map(|t| t.token).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            TokType::IConstant(0),
+            TokType::IConstant(42),
+            TokType::FConstant(1.5),
+            TokType::FConstant(10.0),
+            TokType::FConstant(0.5),
+            TokType::FConstant("1e-3".parse().unwrap()),
+            TokType::FConstant(25000.0),
+            TokType::FConstant(100.0),
+            TokType::FConstant("0.1".parse().unwrap()),
+            TokType::FConstant("3.14159265358979323846".parse().unwrap()),
+            TokType::IConstant(i64::MAX),
+            TokType::FConstant(9_223_372_036_854_775_808.0),
+            TokType::EOF,
+        ]
+    );
+
+    let tokens = TokType::lex("v.x[0]").unwrap();
+    assert_eq!(tokens[1].token, TokType::Dot);
+
+    assert_eq!(TokType::lex("1e+;"), Err(LexError::InvalidNumber { span: Span::new(0, 3) }));
+}
+
+#[test]
+fn lex_string_escapes() {
+    let scad = r#""a\"b" "tab\tnew\nline\\" "\x41\u263A\U01F600" 'it\'s' "plain""#;
+    let tokens = TokType::lex(scad).unwrap();
+
+    let strings: Vec<(&str, &str)> = tokens
+        .iter()
+        .filter_map(|t| match &t.token {
+            TokType::StringLiteral(raw, value) => Some((raw.as_ref(), value.as_ref())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        strings,
+        vec![
+            (r#"a\"b"#, "a\"b"),
+            (r#"tab\tnew\nline\\"#, "tab\tnew\nline\\"),
+            (r#"\x41\u263A\U01F600"#, "A☺😀"),
+            (r#"it\'s"#, "it's"),
+            ("plain", "plain"),
+        ]
+    );
+    assert_eq!(tokens[0].span, Span::new(0, 6));
+    match &tokens[4].token {
+        TokType::StringLiteral(_, Cow::Borrowed(_)) => {}
+        other => panic!("expected a borrowed value, got {:?}", other),
+    }
+
+    assert_eq!(TokType::lex(r#"x = "a\qb";"#), Err(LexError::InvalidEscape { span: Span::new(6, 8) }));
+    assert_eq!(TokType::lex(r#""\x80""#), Err(LexError::InvalidEscape { span: Span::new(1, 5) }));
+    assert_eq!(TokType::lex(r#""\u12""#), Err(LexError::InvalidEscape { span: Span::new(1, 5) }));
+
+    let (tokens, errors) = TokType::lex_recovering(r#"x = "a\qb";"#);
+    assert_eq!(errors, vec![LexError::InvalidEscape { span: Span::new(6, 8) }]);
+    assert_eq!(tokens[2], LexType::new(TokType::Error, Span::new(4, 10)));
+    assert_eq!(tokens[3].token, TokType::Semicolon);
+}
+
+#[test]
+fn lex_unterminated() {
+    let scad = "a = \"abc;\nb = 2;\n";
+    assert_eq!(TokType::lex(scad), Err(LexError::UnterminatedString { span: Span::new(4, 5) }));
+    let (tokens, _) = TokType::lex_recovering(scad);
+    assert_eq!(tokens[2], LexType::new(TokType::Error, Span::new(4, 17)));
+    assert_eq!(tokens[3].token, TokType::EOF);
+
+    // a trailing backslash must not escape the end of the input
+    assert_eq!(TokType::lex("'abc\\"), Err(LexError::UnterminatedString { span: Span::new(0, 1) }));
+
+    let scad = "a = 1; /* comment\n b = 2;";
+    assert_eq!(TokType::lex(scad), Err(LexError::UnterminatedBlockComment { span: Span::new(7, 9) }));
+
+    let tokens = TokType::lex("/* one **/ a /***/ b /* * / */").unwrap();
+    let tokens: Vec<TokType> = tokens.into_iter().map(|t| t.token).collect();
+    assert_eq!(tokens, vec![TokType::IDENTIFIER("a".into()), TokType::IDENTIFIER("b".into()), TokType::EOF]);
+}
+
+#[test]
+fn lex_keywords() {
+    let scad = "module function if else let each for intersection_for assert echo include use true false undef modules";
+    let tokens: Vec<TokType> = TokType::lex(scad).unwrap().into_iter().map(|t| t.token).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            TokType::Module,
+            TokType::Function,
+            TokType::IF,
+            TokType::ELSE,
+            TokType::Let,
+            TokType::Each,
+            TokType::For,
+            TokType::IntersectionFor,
+            TokType::Assert,
+            TokType::Echo,
+            TokType::Include,
+            TokType::Use,
+            TokType::Bool(true),
+            TokType::Bool(false),
+            TokType::Undef,
+            TokType::IDENTIFIER("modules".into()),
+            TokType::EOF,
+        ]
+    );
+
+    let tokens: Vec<TokType> = TokType::lex("a--b^2").unwrap().into_iter().map(|t| t.token).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokType::IDENTIFIER("a".into()),
+            TokType::Minus,
+            TokType::Minus,
+            TokType::IDENTIFIER("b".into()),
+            TokType::ExclusiveOr,
+            TokType::IConstant(2),
+            TokType::EOF,
+        ]
+    );
+}
+
+fn modifiers(src: &str) -> Vec<(&str, TokType<'_>)> {
+    TokType::lex(src)
+        .unwrap()
+        .into_iter()
+        .filter(|t| {
+            matches!(
+                t.token,
+                TokType::Exclamation
+                    | TokType::Multi
+                    | TokType::Mod
+                    | TokType::RootModifier
+                    | TokType::DisableModifier
+                    | TokType::BackgroundModifier
+                    | TokType::DebugModifier
+            )
+        })
+        .map(|t| (&src[t.span.range()], t.token))
+        .collect()
+}
+
+#[test]
+fn lex_modifiers() {
+    assert_eq!(
+        modifiers("!cube(); *sphere(); %cylinder(); #square();"),
+        vec![
+            ("!", TokType::RootModifier),
+            ("*", TokType::DisableModifier),
+            ("%", TokType::BackgroundModifier),
+            ("#", TokType::DebugModifier),
+        ]
+    );
+    assert_eq!(
+        modifiers("if (a) !b(); else *c(); { %d(); } include <x.scad>\n#e();"),
+        vec![
+            ("!", TokType::RootModifier),
+            ("*", TokType::DisableModifier),
+            ("%", TokType::BackgroundModifier),
+            ("#", TokType::DebugModifier),
+        ]
+    );
+    assert_eq!(
+        modifiers("translate([1 * 2, 0]) rotate(90) *#cube(a % 2);"),
+        vec![
+            ("*", TokType::Multi),
+            ("*", TokType::DisableModifier),
+            ("#", TokType::DebugModifier),
+            ("%", TokType::Mod),
+        ]
+    );
+    // operators inside expressions stay operators
+    assert_eq!(
+        modifiers("x = (a) * !b; function f(x) = (x) % 2; module m(s = (1) * 2) !c();"),
+        vec![
+            ("*", TokType::Multi),
+            ("!", TokType::Exclamation),
+            ("%", TokType::Mod),
+            ("*", TokType::Multi),
+            ("!", TokType::RootModifier),
+        ]
+    );
+}
+
+#[test]
+fn lex_paths() {
+    let scad = "include <lib/gears.scad>\nuse\t<My Parts/nut-m3.scad>;\nuse = 1;";
+    let tokens = TokType::lex(scad).unwrap();
+
+    assert_eq!(tokens[0], LexType::new(TokType::IncludePath("lib/gears.scad".into()), Span::new(0, 24)));
+    assert_eq!(tokens[1], LexType::new(TokType::UsePath("My Parts/nut-m3.scad".into()), Span::new(25, 51)));
+    assert_eq!(tokens[2].token, TokType::Semicolon);
+    assert_eq!(tokens[3], LexType::new(TokType::Use, Span::new(53, 56)));
+    assert_eq!(tokens[4].token, TokType::Assign);
 
-    assert_eq!(scad, Err("unexpected Character § at 4:21".to_string()));
-}
\ No newline at end of file
+    assert_eq!(
+        TokType::lex("include <lib/gears.scad\ncube();"),
+        Err(LexError::UnterminatedPath { span: Span::new(8, 9) })
+    );
+}
+
+#[test]
+fn lex_lossless() {
+    let scad = "// header\r\n/* [Size] */\nsize = 10; // [1:100]\n\tcube(size);  /* trailing */";
+    let tokens = TokType::lex_lossless(scad).unwrap();
+
+    let text: String = tokens.iter().map(|t| &scad[t.span.range()]).collect();
+    assert_eq!(text, scad);
+
+    assert_eq!(tokens[0], LexType::new(TokType::Comment(" header".into()), Span::new(0, 9)));
+    assert_eq!(tokens[1], LexType::new(TokType::Newline, Span::new(9, 11)));
+    assert_eq!(tokens[2], LexType::new(TokType::BlockComment(" [Size] ".into()), Span::new(11, 23)));
+    assert_eq!(tokens[3].token, TokType::Newline);
+    assert_eq!(tokens[11], LexType::new(TokType::Comment(" [1:100]".into()), Span::new(35, 45)));
+    assert_eq!(tokens[13], LexType::new(TokType::Whitespace, Span::new(46, 47)));
+
+    let without: Vec<_> = tokens.into_iter().filter(|t| !t.token.is_trivia()).collect();
+    assert_eq!(without, TokType::lex(scad).unwrap());
+
+    let scad = "a = \"b;\n";
+    let (tokens, _) = Lexer::new(scad).with_trivia().lex_recovering();
+    let text: String = tokens.iter().map(|t| &scad[t.span.range()]).collect();
+    assert_eq!(text, scad);
+}
+
+#[test]
+fn lex_unicode_identifiers() {
+    let scad = "höhe = 10; // Höhe in mm\nwürfel_2 = höhe;";
+    let tokens = TokType::lex(scad).unwrap();
+
+    assert_eq!(tokens[0], LexType::new(TokType::IDENTIFIER("höhe".into()), Span::new(0, 5)));
+    assert_eq!(tokens[4], LexType::new(TokType::IDENTIFIER("würfel_2".into()), Span::new(27, 36)));
+    assert_eq!(tokens[6], LexType::new(TokType::IDENTIFIER("höhe".into()), Span::new(39, 44)));
+}
+
+#[test]
+fn lex_matches_char_baseline() {
+    let pieces = [
+        "x", "höhe", "$fn", "_a1", "module", "include", "use", " <a.scad>", "<", ">", "\n", "\r\n", "\r", " ", "\t",
+        "0", "42", "1.5", ".5", "1e", "2.5e-3", "1E+4", "99999999999999999999", "\"", "'", "\\", "\\u263A",
+        "\\x4", "\\n", "//", "/*", "*/", "#", "!", "*", "%", "!=", "==", "<=", "<<", ">>", "&&", "||", "&", "|", "^",
+        "(", ")", "{", "}", "[", "]", ";", ",", ".", "?", ":", "=", "§", "é",
+    ];
+
+    let mut seed: u64 = 7;
+    let mut random = |n: usize| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % n
+    };
+    for _ in 0..20_000 {
+        let len = random(16);
+        let src: String = (0..len).map(|_| pieces[random(pieces.len())]).collect();
+        let bytes: Vec<_> = Lexer::new(&src).collect();
+        let chars: Vec<_> = CharLexer::new(&src).collect();
+        assert_eq!(bytes, chars, "{:?}", src);
+        let bytes: Vec<_> = Lexer::new(&src).with_trivia().collect();
+        let chars: Vec<_> = CharLexer::new(&src).with_trivia().collect();
+        assert_eq!(bytes, chars, "{:?}", src);
+    }
+}
+
+#[test]
+fn source_map_columns() {
+    let scad = "// Größe 😀\nx = \"ä😀\"; y";
+    let map = SourceMap::new(scad);
+    let y = scad.rfind('y').unwrap();
+
+    assert_eq!(map.line_col(y), (2, 11));
+    assert_eq!(map.line_col_utf8(y), (2, 15));
+    assert_eq!(map.line_col_utf16(y), (2, 12));
+    assert_eq!(map.offset_utf16(2, 12), Some(y));
+    assert_eq!(map.offset(2, 11), Some(y));
+    assert_eq!(map.offset_utf8(2, 15), Some(y));
+
+    // end of the first line, and positions inside a surrogate pair
+    assert_eq!(map.line_col_utf16(15), (1, 12));
+    assert_eq!(map.offset_utf16(1, 12), Some(15));
+    assert_eq!(map.offset_utf16(1, 11), None);
+    assert_eq!(map.offset_utf16(3, 1), None);
+}
+
+#[test]
+fn source_db() {
+    let mut db = SourceDb::new();
+    let main = db.add("main.scad", "include <lib.scad>\nLogo(50);\n");
+    let lib = db.add("lib/lib.scad", "module Logo(size) {\n  cube(size) §\n}\n");
+    assert_eq!(main, FileId::default());
+    assert_ne!(main, lib);
+    assert_eq!(db.add("main.scad", "Logo(50);\n"), main);
+    assert_eq!(db.len(), 2);
+    assert_eq!(db.file_id(Path::new("lib/lib.scad")), Some(lib));
+    assert_eq!(db.text(main), "Logo(50);\n");
+
+    let (tokens, errors) = db.lex(lib);
+    assert!(tokens.iter().all(|t| t.span.file == lib));
+    assert_eq!(errors, vec![LexError::UnexpectedChar { ch: '§', span: Span::new(33, 35).in_file(lib) }]);
+    assert_eq!(errors[0].file(), lib);
+    assert_eq!(db.snippet(errors[0].span()), "§");
+    assert_eq!(db.location(errors[0].span()).to_string(), "lib/lib.scad:2:14");
+    assert_eq!(db.location(Span::new(0, 4)).to_string(), "main.scad:1:1");
+
+    let err = Lexer::new("\"open").with_file(lib).next().unwrap().unwrap_err();
+    assert_eq!(err.span(), Span::new(0, 1).in_file(lib));
+}
+
+fn apply(src: &str, edit: &TextEdit) -> String {
+    format!("{}{}{}", &src[..edit.range.start], edit.text, &src[edit.range.end..])
+}
+
+#[test]
+fn relex() {
+    let src = "a = 1;\nb = \"text\";\n/* c */ cube(a);\n";
+    let mut lexer = IncrementalLexer::new(src);
+
+    // typing into an identifier only replaces the tokens around it
+    let edit = TextEdit::new(Span::new(1, 1), "bc");
+    let new = apply(src, &edit);
+    let changed = lexer.relex(&new, &edit);
+    assert_eq!(lexer.tokens(), IncrementalLexer::new(&new).tokens());
+    assert_eq!(lexer.tokens()[0].token, TokType::IDENTIFIER("abc".into()));
+    assert_eq!(changed, 0..1);
+
+    // opening a string swallows the rest of the file
+    let src = new;
+    let edit = TextEdit::new(Span::new(6, 6), "\"");
+    let new = apply(&src, &edit);
+    lexer.relex(&new, &edit);
+    assert_eq!(lexer.tokens(), IncrementalLexer::new(&new).tokens());
+    assert_eq!(lexer.errors(), IncrementalLexer::new(&new).errors());
+
+    // and closing it again restores the old tokens
+    let edit = TextEdit::new(Span::new(6, 7), "");
+    let new = apply(&new, &edit);
+    lexer.relex(&new, &edit);
+    assert_eq!(new, src);
+    assert_eq!(lexer.tokens(), IncrementalLexer::new(&src).tokens());
+    assert!(lexer.errors().is_empty());
+
+    // `include` and `use` look past trivia for their path
+    for src in &["include \nx;", "use  \n\nx;"] {
+        let mut lexer = IncrementalLexer::with_trivia(src);
+        let at = src.find('x').unwrap();
+        let edit = TextEdit::new(Span::new(at, at + 1), "<a>");
+        let new = apply(src, &edit);
+        lexer.relex(&new, &edit);
+        assert_eq!(lexer.tokens(), IncrementalLexer::with_trivia(&new).tokens(), "{:?}", new);
+        assert!(matches!(&lexer.tokens()[0].token, TokType::IncludePath(path) | TokType::UsePath(path) if path == "a"));
+    }
+}
+
+#[test]
+fn relex_random_edits() {
+    let base = "include <a.scad>\nx = [1, 2.5e3, .5]; // size\n/* block\n comment */ s = \"a\\\"b\";\nmodule m() { #cube(x); *m() %s(x * 2); }\n";
+    let inserts = [
+        "\"", "/*", "*/", "//", "\n", " ", "<", "=", "include <", "1.", "e", "ab", "§", "\\", "'", "", "!", "*", ";", "(", ")",
+    ];
+
+    for &trivia in &[false, true] {
+        let mut src = base.to_string();
+        let mut lexer = if trivia { IncrementalLexer::with_trivia(&src) } else { IncrementalLexer::new(&src) };
+        let mut seed: u64 = 42;
+        let mut random = |n: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % n
+        };
+        for _ in 0..500 {
+            let mut start = random(src.len() + 1);
+            while !src.is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = (start + random(4)).min(src.len());
+            while !src.is_char_boundary(end) {
+                end += 1;
+            }
+            let text = inserts[random(inserts.len())];
+            let edit = TextEdit::new(Span::new(start, end), text);
+            let new = apply(&src, &edit);
+            lexer.relex(&new, &edit);
+
+            let expected = if trivia { IncrementalLexer::with_trivia(&new) } else { IncrementalLexer::new(&new) };
+            assert_eq!(lexer.tokens(), expected.tokens(), "after {:?} on {:?}", edit, src);
+            assert_eq!(lexer.errors(), expected.errors(), "after {:?} on {:?}", edit, src);
+            src = new;
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    let tokens = TokType::lex("x = \"a\\tb\"; cube(1.5);").unwrap();
+
+    let json = serde_json::to_string(&tokens[0]).unwrap();
+    assert_eq!(json, r#"{"token":{"kind":"IDENTIFIER","value":"x"},"span":{"start":0,"end":1,"file":0}}"#);
+    let json = serde_json::to_string(&tokens[1].token).unwrap();
+    assert_eq!(json, r#"{"kind":"Assign"}"#);
+
+    let json = serde_json::to_string(&tokens).unwrap();
+    let back: Vec<LexType> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, tokens);
+
+    let err = LexError::UnexpectedChar { ch: '§', span: Span::new(2, 4) };
+    let back: LexError = serde_json::from_str(&serde_json::to_string(&err).unwrap()).unwrap();
+    assert_eq!(back, err);
+}
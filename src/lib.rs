@@ -1,4 +1,10 @@
 #[macro_use] extern crate log;
 
+pub mod customizer;
+pub mod diagnostics;
+pub mod highlight;
 pub mod lexer;
+pub mod parser;
+
+mod json;
 
@@ -1,14 +1,98 @@
 #[macro_use] extern crate log;
 
-use openscad::lexer::TokType;
+use openscad::diagnostics::{Diagnostic, Renderer};
+use openscad::lexer::{FileId, SourceDb};
+use openscad::parser::parse_file_recovering;
+
+const USAGE: &str = "usage: openscad [--tokens] [--color] [--json] [FILE]";
 
 fn main() {
     env_logger::init();
-    info!("reading main.scad");
 
-    let file = std::fs::read_to_string("main.scad").expect("could not open file");
+    let mut tokens = false;
+    let mut color = false;
+    let mut json = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--tokens" => tokens = true,
+            "--color" => color = true,
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    let path = path.unwrap_or_else(|| "main.scad".to_string());
+    info!("reading {}", path);
+
+    let mut db = SourceDb::new();
+    let file = db.load(&path).expect("could not open file");
+
+    let renderer = Renderer::new().with_color(color);
+    let report = |diagnostic: Diagnostic| {
+        if json {
+            eprintln!("{}", diagnostic.to_json(&db));
+        } else {
+            eprint!("{}", renderer.render(&db, &diagnostic));
+        }
+    };
+
+    if tokens {
+        let (status, errors) = dump_tokens(&db, file);
+        errors.into_iter().for_each(report);
+        std::process::exit(status);
+    }
+
+    let (program, errors) = parse_file_recovering(&db, file);
+    println!("{:#?}", program);
+    if !errors.is_empty() {
+        errors.into_iter().map(Diagnostic::from).for_each(report);
+        std::process::exit(1);
+    }
+}
+
+/// Prints the tokens of `file` as JSON Lines, one object with the token kind,
+/// value, source text and span per line.
+#[cfg(feature = "serde")]
+fn dump_tokens(db: &SourceDb, file: FileId) -> (i32, Vec<Diagnostic>) {
+    use openscad::lexer::{Span, TokType};
+    use std::io::Write;
+
+    #[derive(serde::Serialize)]
+    struct Line<'a> {
+        #[serde(flatten)]
+        token: &'a TokType<'a>,
+        text: &'a str,
+        span: Span,
+    }
 
-    let lex = TokType::lex(&file).expect("msg: &str");
+    let (tokens, errors) = db.lex(file);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for tok in &tokens {
+        let line = Line {
+            token: &tok.token,
+            text: db.snippet(tok.span),
+            span: tok.span,
+        };
+        serde_json::to_writer(&mut out, &line).expect("could not serialize token");
+        writeln!(out).expect("could not write to stdout");
+    }
+
+    let status = if errors.is_empty() { 0 } else { 1 };
+    (status, errors.into_iter().map(Diagnostic::from).collect())
+}
 
-    println!("LEX: {:?}", lex);
+#[cfg(not(feature = "serde"))]
+fn dump_tokens(_db: &SourceDb, _file: FileId) -> (i32, Vec<Diagnostic>) {
+    let diagnostic = Diagnostic::error("--tokens needs the `serde` feature")
+        .with_help("rebuild with `--features serde`");
+    (2, vec![diagnostic])
 }
@@ -0,0 +1,377 @@
+use crate::lexer::Span;
+
+/// A parsed source file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatementKind {
+    /// `;`
+    Empty,
+    /// `{ ... }`
+    Block(Vec<Statement>),
+    /// `include <path>`
+    Include(String),
+    /// `use <path>`
+    Use(String),
+    Assignment(Assignment),
+    Module(ModuleDefinition),
+    Function(FunctionDefinition),
+    /// `cube(10);` or `translate(v) child`, the child being `Empty` for `;`.
+    Instantiation {
+        name: Ident,
+        arguments: Vec<Argument>,
+        child: Box<Statement>,
+    },
+    /// A statement prefixed by `!`, `*`, `%` or `#`.
+    Modifier {
+        modifier: Modifier,
+        child: Box<Statement>,
+    },
+    If {
+        condition: Expr,
+        then: Box<Statement>,
+        otherwise: Option<Box<Statement>>,
+    },
+    For {
+        assignments: Vec<Assignment>,
+        child: Box<Statement>,
+    },
+    IntersectionFor {
+        assignments: Vec<Assignment>,
+        child: Box<Statement>,
+    },
+    Let {
+        assignments: Vec<Assignment>,
+        child: Box<Statement>,
+    },
+    Echo {
+        arguments: Vec<Argument>,
+        child: Box<Statement>,
+    },
+    Assert {
+        arguments: Vec<Argument>,
+        child: Box<Statement>,
+    },
+    /// Source the parser could not make sense of and skipped.
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Modifier {
+    /// `!`, show only this subtree.
+    Root,
+    /// `*`, ignore this subtree.
+    Disable,
+    /// `%`, show transparently without rendering.
+    Background,
+    /// `#`, render highlighted.
+    Debug,
+}
+
+/// `name = value`, as a statement or inside `for`/`let` parentheses.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Assignment {
+    pub name: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ModuleDefinition {
+    pub name: Ident,
+    pub parameters: Vec<Parameter>,
+    pub body: Box<Statement>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionDefinition {
+    pub name: Ident,
+    pub parameters: Vec<Parameter>,
+    pub body: Expr,
+}
+
+/// A parameter of a module or function definition, with its default value.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub name: Ident,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+/// A positional or `name = value` argument of a call.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Argument {
+    pub name: Option<Ident>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Undef,
+    Variable(String),
+    /// `[a, b, c]`, where the elements may also be list comprehensions.
+    Vector(Vec<Expr>),
+    /// `[start:end]` or `[start:step:end]`
+    Range {
+        start: Box<Expr>,
+        step: Option<Box<Expr>>,
+        end: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `condition ? then : otherwise`
+    Ternary {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// `callee(arguments)`, the callee being a function name or any
+    /// expression evaluating to a function, like in `g(1)(2)`.
+    Call {
+        callee: Box<Expr>,
+        arguments: Vec<Argument>,
+    },
+    /// `function (x) x * 2`, which evaluates to a closure capturing the
+    /// `free_variables` of the literal from the scope it is defined in.
+    FunctionLiteral {
+        parameters: Vec<Parameter>,
+        body: Box<Expr>,
+    },
+    /// `object[index]`
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// `object.name`, like `v.x`
+    Member {
+        object: Box<Expr>,
+        name: Ident,
+    },
+    /// `let (a = 1) body`, the body being a list comprehension element
+    /// when used as one.
+    Let {
+        assignments: Vec<Assignment>,
+        body: Box<Expr>,
+    },
+    /// `assert(condition) body`, the body being optional.
+    Assert {
+        arguments: Vec<Argument>,
+        body: Option<Box<Expr>>,
+    },
+    /// `echo(values) body`, the body being optional.
+    Echo {
+        arguments: Vec<Argument>,
+        body: Option<Box<Expr>>,
+    },
+    /// `for (i = values) body` as a vector element, adding the body's
+    /// values for each combination of the assignments.
+    ComprehensionFor {
+        assignments: Vec<Assignment>,
+        body: Box<Expr>,
+    },
+    /// `for (init; condition; update) body` as a vector element.
+    ComprehensionCFor {
+        init: Vec<Assignment>,
+        condition: Box<Expr>,
+        update: Vec<Assignment>,
+        body: Box<Expr>,
+    },
+    /// `if (condition) then else otherwise` as a vector element, adding
+    /// nothing when the condition is false and there is no else branch.
+    ComprehensionIf {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Option<Box<Expr>>,
+    },
+    /// `each body` as a vector element, adding the values of a list or
+    /// range one by one.
+    Each(Box<Expr>),
+    /// An argument or vector element the parser could not make sense of.
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum UnaryOp {
+    /// `-`
+    Negate,
+    /// `+`
+    Plus,
+    /// `!`
+    Not,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    /// `^`, right-associative
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+/// Names an expression reads from its enclosing scope, in order of first use.
+#[derive(Default)]
+struct Names {
+    free: Vec<String>,
+    called: Vec<String>,
+}
+
+impl Expr {
+    /// Names of the variables the expression reads from its enclosing scope,
+    /// in order of first use. Special `$` variables are dynamically scoped
+    /// and never captured, so they are not included.
+    pub fn free_variables(&self) -> Vec<String> {
+        let mut names = Names::default();
+        self.collect_names(&mut Vec::new(), &mut names);
+        names.free
+    }
+
+    /// Names of the free variables called like a function, like `f` in
+    /// `f(x)`. They resolve to a function definition, or else to a variable
+    /// holding a function literal, so they are also among `free_variables`.
+    pub fn called_names(&self) -> Vec<String> {
+        let mut names = Names::default();
+        self.collect_names(&mut Vec::new(), &mut names);
+        names.called
+    }
+
+    fn collect_names<'a>(&'a self, bound: &mut Vec<&'a str>, names: &mut Names) {
+        let scope = bound.len();
+        match &self.kind {
+            ExprKind::Number(_) | ExprKind::String(_) | ExprKind::Bool(_) | ExprKind::Undef | ExprKind::Error => {}
+            ExprKind::Variable(name) => {
+                if !name.starts_with('$') && !bound.contains(&name.as_str()) && !names.free.contains(name) {
+                    names.free.push(name.clone());
+                }
+            }
+            ExprKind::Vector(items) => {
+                for item in items {
+                    item.collect_names(bound, names);
+                }
+            }
+            ExprKind::Range { start, step, end } => {
+                start.collect_names(bound, names);
+                if let Some(step) = step {
+                    step.collect_names(bound, names);
+                }
+                end.collect_names(bound, names);
+            }
+            ExprKind::Unary { operand, .. } => operand.collect_names(bound, names),
+            ExprKind::Binary { lhs, rhs, .. } => {
+                lhs.collect_names(bound, names);
+                rhs.collect_names(bound, names);
+            }
+            ExprKind::Ternary { condition, then, otherwise } => {
+                condition.collect_names(bound, names);
+                then.collect_names(bound, names);
+                otherwise.collect_names(bound, names);
+            }
+            ExprKind::Call { callee, arguments } => {
+                if let ExprKind::Variable(name) = &callee.kind {
+                    if !name.starts_with('$') && !bound.contains(&name.as_str()) && !names.called.contains(name) {
+                        names.called.push(name.clone());
+                    }
+                }
+                callee.collect_names(bound, names);
+                for argument in arguments {
+                    argument.value.collect_names(bound, names);
+                }
+            }
+            ExprKind::FunctionLiteral { parameters, body } => {
+                // defaults are evaluated where the function is defined
+                for parameter in parameters {
+                    if let Some(default) = &parameter.default {
+                        default.collect_names(bound, names);
+                    }
+                }
+                bound.extend(parameters.iter().map(|p| p.name.name.as_str()));
+                body.collect_names(bound, names);
+            }
+            ExprKind::Index { object, index } => {
+                object.collect_names(bound, names);
+                index.collect_names(bound, names);
+            }
+            ExprKind::Member { object, .. } => object.collect_names(bound, names),
+            // each assignment sees the ones before it, like nested loops
+            ExprKind::Let { assignments, body } | ExprKind::ComprehensionFor { assignments, body } => {
+                bind(assignments, bound, names);
+                body.collect_names(bound, names);
+            }
+            ExprKind::Assert { arguments, body } | ExprKind::Echo { arguments, body } => {
+                for argument in arguments {
+                    argument.value.collect_names(bound, names);
+                }
+                if let Some(body) = body {
+                    body.collect_names(bound, names);
+                }
+            }
+            ExprKind::ComprehensionCFor { init, condition, update, body } => {
+                bind(init, bound, names);
+                condition.collect_names(bound, names);
+                for assignment in update {
+                    assignment.value.collect_names(bound, names);
+                }
+                body.collect_names(bound, names);
+            }
+            ExprKind::ComprehensionIf { condition, then, otherwise } => {
+                condition.collect_names(bound, names);
+                then.collect_names(bound, names);
+                if let Some(otherwise) = otherwise {
+                    otherwise.collect_names(bound, names);
+                }
+            }
+            ExprKind::Each(body) => body.collect_names(bound, names),
+        }
+        bound.truncate(scope);
+    }
+}
+
+fn bind<'a>(assignments: &'a [Assignment], bound: &mut Vec<&'a str>, names: &mut Names) {
+    for assignment in assignments {
+        assignment.value.collect_names(bound, names);
+        bound.push(&assignment.name.name);
+    }
+}
@@ -0,0 +1,70 @@
+use std::{error, fmt};
+
+use crate::lexer::{LexError, Span};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    Lex(LexError),
+    /// `expected` and `found` are descriptions like "`)`" or "end of file".
+    Expected { expected: String, found: String, span: Span },
+    /// The bracket `delimiter` opened at `open`, on the 1-based `line` and
+    /// `column`, is not closed where `span` is.
+    Unclosed {
+        delimiter: char,
+        open: Span,
+        line: usize,
+        column: usize,
+        span: Span,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Lex(err) => err.span(),
+            ParseError::Expected { span, .. } | ParseError::Unclosed { span, .. } => *span,
+        }
+    }
+}
+
+impl From<LexError> for ParseError {
+    fn from(err: LexError) -> Self {
+        ParseError::Lex(err)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Lex(err) => write!(f, "{}", err),
+            ParseError::Expected { expected, found, .. } => write!(f, "expected {}, found {}", expected, found),
+            ParseError::Unclosed { delimiter, line, column, .. } => write!(
+                f,
+                "expected `{}` to close `{}` opened at {}:{}",
+                closing(*delimiter),
+                delimiter,
+                line,
+                column
+            ),
+        }
+    }
+}
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParseError::Lex(err) => Some(err),
+            ParseError::Expected { .. } | ParseError::Unclosed { .. } => None,
+        }
+    }
+}
+
+/// The closing bracket of the opening bracket `delimiter`.
+pub(crate) fn closing(delimiter: char) -> char {
+    match delimiter {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        _ => delimiter,
+    }
+}
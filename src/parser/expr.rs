@@ -0,0 +1,410 @@
+use crate::lexer::{Span, TokType};
+
+use super::ast::*;
+use super::{ParseError, Parser};
+
+/// Binding power of `?:`, the loosest binding operator.
+const TERNARY: u8 = 1;
+/// Binding power of the operand of a prefix operator: tighter than `*`, but
+/// looser than `^`, so `-2^2` is `-(2^2)` like upstream.
+const PREFIX: u8 = 14;
+
+/// Left and right binding power of a binary operator, following the
+/// precedence of the reference grammar from loosest to tightest:
+/// `||`, `&&`, `== !=`, `< <= > >=`, `+ -`, `* / %` and `^`. All are
+/// left-associative but `^`, whose right binding power is the lower one.
+fn infix(token: &TokType) -> Option<(BinaryOp, u8, u8)> {
+    let op = match token {
+        TokType::OrOp => (BinaryOp::Or, 2, 3),
+        TokType::AndOp => (BinaryOp::And, 4, 5),
+        TokType::EqOp => (BinaryOp::Equal, 6, 7),
+        TokType::NeOp => (BinaryOp::NotEqual, 6, 7),
+        TokType::Lt => (BinaryOp::Less, 8, 9),
+        TokType::LeOp => (BinaryOp::LessEqual, 8, 9),
+        TokType::Gt => (BinaryOp::Greater, 8, 9),
+        TokType::GeOp => (BinaryOp::GreaterEqual, 8, 9),
+        TokType::Plus => (BinaryOp::Add, 10, 11),
+        TokType::Minus => (BinaryOp::Subtract, 10, 11),
+        TokType::Multi => (BinaryOp::Multiply, 12, 13),
+        TokType::Splash => (BinaryOp::Divide, 12, 13),
+        TokType::Mod => (BinaryOp::Modulo, 12, 13),
+        TokType::ExclusiveOr => (BinaryOp::Power, 16, 15),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Whether an expression can start with the token, to tell `assert(x);`
+/// apart from `assert(x) body`.
+fn starts_expr(token: &TokType) -> bool {
+    matches!(
+        token,
+        TokType::IConstant(_)
+            | TokType::FConstant(_)
+            | TokType::StringLiteral(..)
+            | TokType::Bool(_)
+            | TokType::Undef
+            | TokType::IDENTIFIER(_)
+            | TokType::LParen
+            | TokType::LBracket
+            | TokType::Minus
+            | TokType::Plus
+            | TokType::Exclamation
+            | TokType::Let
+            | TokType::Assert
+            | TokType::Echo
+            | TokType::Function
+    )
+}
+
+/// The parenthesized part of a comprehension `for`.
+enum ForHeader {
+    /// `(i = values)`
+    Assignments(Vec<Assignment>),
+    /// `(init; condition; update)`
+    CStyle {
+        init: Vec<Assignment>,
+        condition: Box<Expr>,
+        update: Vec<Assignment>,
+    },
+}
+
+/// Whether the element is a list comprehension rather than an expression.
+fn is_comprehension(element: &Expr) -> bool {
+    match &element.kind {
+        ExprKind::ComprehensionFor { .. }
+        | ExprKind::ComprehensionCFor { .. }
+        | ExprKind::ComprehensionIf { .. }
+        | ExprKind::Each(_) => true,
+        ExprKind::Let { body, .. } => is_comprehension(body),
+        _ => false,
+    }
+}
+
+impl<'src> Parser<'src> {
+    /// Parses a full expression. `let`, `assert` and `echo` expressions and
+    /// function literals extend as far as possible, so like upstream they
+    /// may only appear where a full expression is expected, not as operands.
+    pub(super) fn expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokType::Let => {
+                self.bump();
+                let assignments = self.assignments()?;
+                let body = Box::new(self.expr()?);
+                ExprKind::Let { assignments, body }
+            }
+            TokType::Assert | TokType::Echo => {
+                let keyword = self.bump().token;
+                let arguments = self.arguments()?;
+                let body = if starts_expr(self.peek()) {
+                    Some(Box::new(self.expr()?))
+                } else {
+                    None
+                };
+                match keyword {
+                    TokType::Assert => ExprKind::Assert { arguments, body },
+                    _ => ExprKind::Echo { arguments, body },
+                }
+            }
+            TokType::Function => {
+                self.bump();
+                let parameters = self.parameters()?;
+                let body = Box::new(self.expr()?);
+                ExprKind::FunctionLiteral { parameters, body }
+            }
+            _ => return self.expr_bp(0),
+        };
+        Ok(Expr { kind, span: start.to(self.previous_span()) })
+    }
+
+    /// Pratt parser for the operators binding tighter than `min_bp`.
+    fn expr_bp(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.prefix()?;
+        loop {
+            if *self.peek() == TokType::QuestionMark {
+                if TERNARY < min_bp {
+                    break;
+                }
+                self.bump();
+                let then = self.expr()?;
+                self.expect(&TokType::Colon, "`:`")?;
+                // the branches are full expressions, which makes `?:` right-associative
+                let otherwise = self.expr()?;
+                let span = lhs.span.to(otherwise.span);
+                lhs = Expr {
+                    kind: ExprKind::Ternary {
+                        condition: Box::new(lhs),
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    },
+                    span,
+                };
+                continue;
+            }
+            let (op, left_bp, right_bp) = match infix(self.peek()) {
+                Some(op) => op,
+                None => break,
+            };
+            if left_bp < min_bp {
+                break;
+            }
+            self.bump();
+            let rhs = self.expr_bp(right_bp)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr {
+                kind: ExprKind::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) },
+                span,
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn prefix(&mut self) -> Result<Expr, ParseError> {
+        let op = match self.peek() {
+            TokType::Minus => UnaryOp::Negate,
+            TokType::Plus => UnaryOp::Plus,
+            TokType::Exclamation => UnaryOp::Not,
+            _ => return self.postfix(),
+        };
+        let start = self.bump().span;
+        let operand = self.expr_bp(PREFIX)?;
+        let span = start.to(operand.span);
+        Ok(Expr {
+            kind: ExprKind::Unary { op, operand: Box::new(operand) },
+            span,
+        })
+    }
+
+    /// A primary expression followed by any calls, indexing and member
+    /// accesses, which bind tighter than all operators.
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+        loop {
+            let start = expr.span;
+            let kind = match self.peek() {
+                TokType::LParen => {
+                    let arguments = self.arguments()?;
+                    ExprKind::Call { callee: Box::new(expr), arguments }
+                }
+                TokType::LBracket => {
+                    let open = self.bump().span;
+                    let index = self.expr()?;
+                    self.close('[', open)?;
+                    ExprKind::Index { object: Box::new(expr), index: Box::new(index) }
+                }
+                TokType::Dot => {
+                    self.bump();
+                    let name = self.ident()?;
+                    ExprKind::Member { object: Box::new(expr), name }
+                }
+                _ => return Ok(expr),
+            };
+            expr = Expr { kind, span: start.to(self.previous_span()) };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokType::IConstant(n) => ExprKind::Number(*n as f64),
+            TokType::FConstant(n) => ExprKind::Number(*n),
+            TokType::StringLiteral(_, value) => ExprKind::String(value.to_string()),
+            TokType::Bool(b) => ExprKind::Bool(*b),
+            TokType::Undef => ExprKind::Undef,
+            TokType::IDENTIFIER(name) => ExprKind::Variable(name.to_string()),
+            // its lex error is already reported
+            TokType::Error => ExprKind::Error,
+            TokType::LParen => {
+                self.bump();
+                let mut expr = self.expr()?;
+                self.close('(', start)?;
+                expr.span = start.to(self.previous_span());
+                return Ok(expr);
+            }
+            TokType::LBracket => return self.vector(),
+            _ => return Err(self.error("expression")),
+        };
+        self.bump();
+        Ok(Expr { kind, span: start })
+    }
+
+    /// `[a, b, c]`, `[start:end]` or `[start:step:end]`. An element failing
+    /// to parse is skipped up to the next `,` or `]` and kept as an `Error`.
+    fn vector(&mut self) -> Result<Expr, ParseError> {
+        let open = self.expect(&TokType::LBracket, "`[`")?;
+        let mut items = Vec::new();
+        while *self.peek() != TokType::RBracket {
+            let start = self.pos;
+            match self.element() {
+                Ok(element) => {
+                    if items.is_empty() && !is_comprehension(&element) && *self.peek() == TokType::Colon {
+                        return self.range(open, element);
+                    }
+                    items.push(element);
+                }
+                Err(err) => {
+                    self.skip_item(err, &TokType::RBracket)?;
+                    items.push(Expr { kind: ExprKind::Error, span: self.span_since(start) });
+                }
+            }
+            if !self.eat(&TokType::Comma) {
+                break;
+            }
+        }
+        self.close('[', open)?;
+        Ok(Expr {
+            kind: ExprKind::Vector(items),
+            span: open.to(self.previous_span()),
+        })
+    }
+
+    /// The rest of a range after its start, `[` being at `open`.
+    fn range(&mut self, open: Span, start: Expr) -> Result<Expr, ParseError> {
+        self.expect(&TokType::Colon, "`:`")?;
+        let second = self.expr()?;
+        let (step, end) = if self.eat(&TokType::Colon) {
+            (Some(Box::new(second)), self.expr()?)
+        } else {
+            (None, second)
+        };
+        self.close('[', open)?;
+        Ok(Expr {
+            kind: ExprKind::Range { start: Box::new(start), step, end: Box::new(end) },
+            span: open.to(self.previous_span()),
+        })
+    }
+
+    /// A vector element: an expression or a list comprehension, optionally
+    /// in parentheses.
+    fn element(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokType::LParen if matches!(self.peek_nth(1), TokType::For | TokType::IF | TokType::Each) => {
+                self.bump();
+                let mut element = self.element()?;
+                self.close('(', start)?;
+                element.span = start.to(self.previous_span());
+                return Ok(element);
+            }
+            TokType::For => {
+                self.bump();
+                self.comprehension_for()?
+            }
+            TokType::IF => {
+                self.bump();
+                let open = self.expect(&TokType::LParen, "`(`")?;
+                let condition = Box::new(self.expr()?);
+                self.close('(', open)?;
+                let then = Box::new(self.element()?);
+                let otherwise = if self.eat(&TokType::ELSE) {
+                    Some(Box::new(self.element()?))
+                } else {
+                    None
+                };
+                ExprKind::ComprehensionIf { condition, then, otherwise }
+            }
+            TokType::Each => {
+                self.bump();
+                ExprKind::Each(Box::new(self.element()?))
+            }
+            // unlike a `let` expression the body may be a comprehension
+            TokType::Let => {
+                self.bump();
+                let assignments = self.assignments()?;
+                let body = Box::new(self.element()?);
+                ExprKind::Let { assignments, body }
+            }
+            _ => return self.expr(),
+        };
+        Ok(Expr { kind, span: start.to(self.previous_span()) })
+    }
+
+    /// The parentheses and body of a comprehension `for`, both the
+    /// `(i = values)` and the C-style `(init; condition; update)` form. A
+    /// header failing to parse is skipped and the `for` kept as an `Error`.
+    fn comprehension_for(&mut self) -> Result<ExprKind, ParseError> {
+        let start = self.pos;
+        let open = self.expect(&TokType::LParen, "`(`")?;
+        let header = match self.for_header(open) {
+            Ok(header) => header,
+            Err(err) => {
+                self.skip_for_header(err, start)?;
+                self.element()?;
+                return Ok(ExprKind::Error);
+            }
+        };
+        let body = Box::new(self.element()?);
+        Ok(match header {
+            ForHeader::Assignments(assignments) => ExprKind::ComprehensionFor { assignments, body },
+            ForHeader::CStyle { init, condition, update } => ExprKind::ComprehensionCFor { init, condition, update, body },
+        })
+    }
+
+    /// The rest of a comprehension `for` header after its `(` at `open`.
+    fn for_header(&mut self, open: Span) -> Result<ForHeader, ParseError> {
+        let assignments = self.assignments_until(&TokType::Semicolon)?;
+        if self.eat(&TokType::Semicolon) {
+            let condition = Box::new(self.expr()?);
+            self.expect(&TokType::Semicolon, "`;`")?;
+            let update = self.assignments_until(&TokType::RParen)?;
+            self.close('(', open)?;
+            return Ok(ForHeader::CStyle { init: assignments, condition, update });
+        }
+        self.close('(', open)?;
+        Ok(ForHeader::Assignments(assignments))
+    }
+
+    /// Skips the rest of a comprehension `for` header that failed to parse,
+    /// up to and including the `)` matching the `(` at token position `open`,
+    /// and records the error. The header's own two `;` are skipped, but a
+    /// third one ends the statement, so the error is returned instead, as it
+    /// is if the header ends at any other token statements resume at.
+    fn skip_for_header(&mut self, err: ParseError, open: usize) -> Result<(), ParseError> {
+        let mut depth = 0usize;
+        let mut semicolons = 0;
+        // walk the consumed part of the header first, to count its brackets
+        // and semicolons
+        let mut i = open + 1;
+        loop {
+            let token = if i < self.pos { &self.tokens[i].token } else { self.peek() };
+            match token {
+                TokType::RParen if depth == 0 => break,
+                TokType::LParen | TokType::LBracket => depth += 1,
+                TokType::RParen | TokType::RBracket if depth > 0 => depth -= 1,
+                TokType::Semicolon if depth > 0 => {}
+                TokType::Semicolon if semicolons < 2 => semicolons += 1,
+                TokType::RBracket
+                | TokType::Semicolon
+                | TokType::LBrace
+                | TokType::RBrace
+                | TokType::Module
+                | TokType::IncludePath(_)
+                | TokType::UsePath(_)
+                | TokType::EOF => return Err(err),
+                _ => {}
+            }
+            if i >= self.pos {
+                self.bump();
+            }
+            i += 1;
+        }
+        if i >= self.pos {
+            self.bump();
+        }
+        self.report(err);
+        Ok(())
+    }
+
+    /// Comma separated assignments up to `)` or `end`, which are not consumed.
+    fn assignments_until(&mut self, end: &TokType) -> Result<Vec<Assignment>, ParseError> {
+        let mut assignments = Vec::new();
+        while *self.peek() != TokType::RParen && self.peek() != end {
+            assignments.push(self.assignment()?);
+            if !self.eat(&TokType::Comma) {
+                break;
+            }
+        }
+        Ok(assignments)
+    }
+}
@@ -0,0 +1,484 @@
+use crate::lexer::{FileId, LexType, Lexer, SourceDb, Span, TokType};
+
+#[cfg(test)]
+mod test;
+
+pub mod ast;
+mod error;
+mod expr;
+
+pub use error::ParseError;
+pub(crate) use error::closing;
+
+use ast::*;
+
+/// Parses a whole source file, failing with its first error.
+pub fn parse(src: &str) -> Result<Program, ParseError> {
+    first_error(parse_recovering(src))
+}
+
+/// Parses a file of the database, with the spans of the tree in that file.
+pub fn parse_file(db: &SourceDb, file: FileId) -> Result<Program, ParseError> {
+    first_error(parse_file_recovering(db, file))
+}
+
+/// Parses a whole source file, recovering from errors. Returns the tree,
+/// with `Error` nodes in place of the source that could not be parsed, and
+/// all lex and parse errors ordered by position.
+pub fn parse_recovering(src: &str) -> (Program, Vec<ParseError>) {
+    Parser::new(src, FileId::default()).program()
+}
+
+/// `parse_recovering` for a file of the database.
+pub fn parse_file_recovering(db: &SourceDb, file: FileId) -> (Program, Vec<ParseError>) {
+    Parser::new(db.text(file), file).program()
+}
+
+fn first_error((program, errors): (Program, Vec<ParseError>)) -> Result<Program, ParseError> {
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(program),
+    }
+}
+
+/// Recursive-descent parser over the significant tokens of a file.
+struct Parser<'src> {
+    src: &'src str,
+    tokens: Vec<LexType<'src>>,
+    pos: usize,
+    /// Errors recovered from so far.
+    errors: Vec<ParseError>,
+}
+
+impl<'src> Parser<'src> {
+    fn new(src: &'src str, file: FileId) -> Self {
+        // the input of a lex error stays as an `Error` token, so the parser
+        // can tell errors following from it apart
+        let (tokens, errors) = Lexer::new(src).with_file(file).lex_recovering();
+        let errors = errors.into_iter().map(ParseError::Lex).collect();
+        Self { src, tokens, pos: 0, errors }
+    }
+
+    fn peek(&self) -> &TokType<'src> {
+        self.peek_nth(0)
+    }
+
+    /// The token `n` tokens ahead, `EOF` past the end.
+    fn peek_nth(&self, n: usize) -> &TokType<'src> {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + n).min(last)].token
+    }
+
+    fn span(&self) -> Span {
+        self.tokens[self.pos].span
+    }
+
+    /// Span of the last consumed token.
+    fn previous_span(&self) -> Span {
+        self.tokens[self.pos.saturating_sub(1)].span
+    }
+
+    fn bump(&mut self) -> LexType<'src> {
+        let tok = self.tokens[self.pos].clone();
+        if tok.token != TokType::EOF {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn eat(&mut self, token: &TokType) -> bool {
+        if self.peek() == token {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &TokType, expected: &str) -> Result<Span, ParseError> {
+        if self.peek() == token {
+            Ok(self.bump().span)
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    /// Error for the current token not being what was `expected`.
+    fn error(&self, expected: &str) -> ParseError {
+        let tok = &self.tokens[self.pos];
+        let found = match tok.token {
+            TokType::EOF => "end of file".to_string(),
+            _ => format!("`{}`", &self.src[tok.span.range()]),
+        };
+        ParseError::Expected {
+            expected: expected.to_string(),
+            found,
+            span: tok.span,
+        }
+    }
+
+    /// Error for the bracket `delimiter` opened at `open` not being closed
+    /// at the current token.
+    fn unclosed(&self, delimiter: char, open: Span) -> ParseError {
+        let before = &self.src[..open.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        ParseError::Unclosed {
+            delimiter,
+            open,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span: self.span(),
+        }
+    }
+
+    /// Expects the closing bracket of `delimiter` opened at `open`.
+    fn close(&mut self, delimiter: char, open: Span) -> Result<Span, ParseError> {
+        let token = match delimiter {
+            '(' => TokType::RParen,
+            '[' => TokType::RBracket,
+            _ => TokType::RBrace,
+        };
+        if *self.peek() == token {
+            Ok(self.bump().span)
+        } else {
+            Err(self.unclosed(delimiter, open))
+        }
+    }
+
+    /// Records an error, unless it is about an `Error` token, whose lex
+    /// error was already recorded, or about the end of a file swallowed by
+    /// one, like an unterminated string.
+    fn report(&mut self, err: ParseError) {
+        let lexed = match err {
+            ParseError::Lex(_) => false,
+            _ => match self.tokens.binary_search_by_key(&err.span().start, |tok| tok.span.start) {
+                Ok(i) => match self.tokens[i].token {
+                    TokType::Error => true,
+                    TokType::EOF => {
+                        i > 0 && self.tokens[i - 1].token == TokType::Error && self.tokens[i - 1].span.end == err.span().start
+                    }
+                    _ => false,
+                },
+                Err(_) => false,
+            },
+        };
+        if !lexed {
+            self.errors.push(err);
+        }
+    }
+
+    /// Span of the tokens consumed since position `start`, or an empty span
+    /// at the current token if there are none.
+    fn span_since(&self, start: usize) -> Span {
+        if self.pos > start {
+            self.tokens[start].span.to(self.previous_span())
+        } else {
+            let span = self.span();
+            Span::new(span.start, span.start).in_file(span.file)
+        }
+    }
+
+    /// Skips tokens up to where a statement can start again: past a `;`, or
+    /// before a `}` or a keyword that only starts statements. Brackets opened
+    /// while skipping are skipped as a whole.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                TokType::EOF => return,
+                TokType::Semicolon if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokType::RBrace
+                | TokType::Module
+                | TokType::Function
+                | TokType::IncludePath(_)
+                | TokType::UsePath(_)
+                | TokType::IF
+                | TokType::For
+                | TokType::IntersectionFor
+                    if depth == 0 =>
+                {
+                    return
+                }
+                TokType::LParen | TokType::LBracket | TokType::LBrace => depth += 1,
+                TokType::RParen | TokType::RBracket | TokType::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    /// Skips the rest of a list item that failed to parse, up to the next `,`
+    /// or `close` outside of nested brackets, and records the error. Returns
+    /// the error instead if the list ends without being closed first.
+    fn skip_item(&mut self, err: ParseError, close: &TokType) -> Result<(), ParseError> {
+        let mut depth = 0usize;
+        loop {
+            let token = self.peek();
+            if depth == 0 && (*token == TokType::Comma || token == close) {
+                break;
+            }
+            match token {
+                TokType::LParen | TokType::LBracket => depth += 1,
+                TokType::RParen | TokType::RBracket if depth > 0 => depth -= 1,
+                TokType::Semicolon if depth > 0 => {}
+                TokType::RParen
+                | TokType::RBracket
+                | TokType::Semicolon
+                | TokType::LBrace
+                | TokType::RBrace
+                | TokType::Module
+                | TokType::IncludePath(_)
+                | TokType::UsePath(_)
+                | TokType::EOF => return Err(err),
+                _ => {}
+            }
+            self.bump();
+        }
+        self.report(err);
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<Ident, ParseError> {
+        match self.peek() {
+            TokType::IDENTIFIER(name) => {
+                let name = name.to_string();
+                let span = self.bump().span;
+                Ok(Ident { name, span })
+            }
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    fn program(mut self) -> (Program, Vec<ParseError>) {
+        let statements = self.statements(&TokType::EOF);
+        let span = Span::new(0, self.src.len()).in_file(self.span().file);
+        // stable, so errors at the same position stay in the order found
+        self.errors.sort_by_key(|err| err.span().start);
+        (Program { statements, span }, self.errors)
+    }
+
+    /// Statements up to `end` or the end of the file, neither consumed. A
+    /// statement failing to parse is recorded as an `Error` statement
+    /// covering the tokens skipped up to where parsing resumes.
+    fn statements(&mut self, end: &TokType) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        while self.peek() != end && *self.peek() != TokType::EOF {
+            let start = self.pos;
+            if *self.peek() == TokType::Error {
+                let span = self.bump().span;
+                statements.push(Statement { kind: StatementKind::Error, span });
+                continue;
+            }
+            match self.statement() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.report(err);
+                    if self.pos == start {
+                        self.bump();
+                    }
+                    self.synchronize();
+                    statements.push(Statement {
+                        kind: StatementKind::Error,
+                        span: self.span_since(start),
+                    });
+                }
+            }
+        }
+        statements
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokType::Semicolon => {
+                self.bump();
+                StatementKind::Empty
+            }
+            TokType::LBrace => {
+                let open = self.bump().span;
+                let statements = self.statements(&TokType::RBrace);
+                // only missing at the end of the file, so keep the block
+                if let Err(err) = self.close('{', open) {
+                    self.report(err);
+                }
+                StatementKind::Block(statements)
+            }
+            TokType::IncludePath(path) => {
+                let path = path.to_string();
+                self.bump();
+                StatementKind::Include(path)
+            }
+            TokType::UsePath(path) => {
+                let path = path.to_string();
+                self.bump();
+                StatementKind::Use(path)
+            }
+            TokType::Module => {
+                self.bump();
+                let name = self.ident()?;
+                let parameters = self.parameters()?;
+                let body = Box::new(self.statement()?);
+                StatementKind::Module(ModuleDefinition { name, parameters, body })
+            }
+            TokType::Function => {
+                self.bump();
+                let name = self.ident()?;
+                let parameters = self.parameters()?;
+                self.expect(&TokType::Assign, "`=`")?;
+                let body = self.expr()?;
+                self.expect(&TokType::Semicolon, "`;`")?;
+                StatementKind::Function(FunctionDefinition { name, parameters, body })
+            }
+            TokType::IDENTIFIER(_) if *self.peek_nth(1) == TokType::Assign => {
+                let assignment = self.assignment()?;
+                self.expect(&TokType::Semicolon, "`;`")?;
+                StatementKind::Assignment(assignment)
+            }
+            _ => return self.child_statement(),
+        };
+        Ok(Statement { kind, span: start.to(self.previous_span()) })
+    }
+
+    /// The statement a module instantiation applies to: `;`, a block or
+    /// another instantiation.
+    fn child_statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokType::Semicolon | TokType::LBrace => return self.statement(),
+            TokType::RootModifier
+            | TokType::DisableModifier
+            | TokType::BackgroundModifier
+            | TokType::DebugModifier => {
+                let modifier = match self.bump().token {
+                    TokType::RootModifier => Modifier::Root,
+                    TokType::DisableModifier => Modifier::Disable,
+                    TokType::BackgroundModifier => Modifier::Background,
+                    _ => Modifier::Debug,
+                };
+                let child = Box::new(self.child_statement()?);
+                StatementKind::Modifier { modifier, child }
+            }
+            TokType::IF => {
+                self.bump();
+                let open = self.expect(&TokType::LParen, "`(`")?;
+                let condition = self.expr()?;
+                self.close('(', open)?;
+                let then = Box::new(self.child_statement()?);
+                let otherwise = if self.eat(&TokType::ELSE) {
+                    Some(Box::new(self.child_statement()?))
+                } else {
+                    None
+                };
+                StatementKind::If { condition, then, otherwise }
+            }
+            TokType::For | TokType::IntersectionFor | TokType::Let => {
+                let keyword = self.bump().token;
+                let assignments = self.assignments()?;
+                let child = Box::new(self.child_statement()?);
+                match keyword {
+                    TokType::For => StatementKind::For { assignments, child },
+                    TokType::IntersectionFor => StatementKind::IntersectionFor { assignments, child },
+                    _ => StatementKind::Let { assignments, child },
+                }
+            }
+            TokType::Echo | TokType::Assert => {
+                let keyword = self.bump().token;
+                let arguments = self.arguments()?;
+                let child = Box::new(self.child_statement()?);
+                match keyword {
+                    TokType::Echo => StatementKind::Echo { arguments, child },
+                    _ => StatementKind::Assert { arguments, child },
+                }
+            }
+            TokType::IDENTIFIER(_) if *self.peek_nth(1) == TokType::LParen => {
+                let name = self.ident()?;
+                let arguments = self.arguments()?;
+                let child = Box::new(self.child_statement()?);
+                StatementKind::Instantiation { name, arguments, child }
+            }
+            // most likely the `;` ending an instantiation like `cube(1)` is missing
+            TokType::RBrace | TokType::EOF if self.pos > 0 && self.tokens[self.pos - 1].token == TokType::RParen => {
+                return Err(self.error("`;`"))
+            }
+            _ => return Err(self.error("statement")),
+        };
+        Ok(Statement { kind, span: start.to(self.previous_span()) })
+    }
+
+    fn assignment(&mut self) -> Result<Assignment, ParseError> {
+        let name = self.ident()?;
+        self.expect(&TokType::Assign, "`=`")?;
+        let value = self.expr()?;
+        let span = name.span.to(value.span);
+        Ok(Assignment { name, value, span })
+    }
+
+    /// Parses a parenthesized, comma separated list, allowing a trailing
+    /// comma. An item failing to parse is skipped up to the next `,` or `)`
+    /// and replaced by what `recovered` returns for the skipped span.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+        recovered: impl Fn(Span) -> Option<T>,
+    ) -> Result<Vec<T>, ParseError> {
+        let open = self.expect(&TokType::LParen, "`(`")?;
+        let mut items = Vec::new();
+        while *self.peek() != TokType::RParen {
+            let start = self.pos;
+            match item(self) {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.skip_item(err, &TokType::RParen)?;
+                    items.extend(recovered(self.span_since(start)));
+                }
+            }
+            if !self.eat(&TokType::Comma) {
+                break;
+            }
+        }
+        self.close('(', open)?;
+        Ok(items)
+    }
+
+    /// `(a = 1, b = [0:2])` of `for` and `let`.
+    fn assignments(&mut self) -> Result<Vec<Assignment>, ParseError> {
+        self.list(Self::assignment, |_| None)
+    }
+
+    fn parameters(&mut self) -> Result<Vec<Parameter>, ParseError> {
+        self.list(
+            |p| {
+                let name = p.ident()?;
+                let default = if p.eat(&TokType::Assign) { Some(p.expr()?) } else { None };
+                let span = name.span.to(p.previous_span());
+                Ok(Parameter { name, default, span })
+            },
+            |_| None,
+        )
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Argument>, ParseError> {
+        self.list(
+            |p| {
+                let start = p.span();
+                let name = match (p.peek(), p.peek_nth(1)) {
+                    (TokType::IDENTIFIER(_), TokType::Assign) => {
+                        let name = p.ident()?;
+                        p.bump();
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                let value = p.expr()?;
+                Ok(Argument { name, span: start.to(value.span), value })
+            },
+            |span| {
+                let value = Expr { kind: ExprKind::Error, span };
+                Some(Argument { name: None, value, span })
+            },
+        )
+    }
+}
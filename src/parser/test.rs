@@ -0,0 +1,532 @@
+use super::ast::*;
+use super::{parse, parse_recovering, ParseError};
+use crate::lexer::{LexError, Span};
+
+fn ident(name: &str, start: usize) -> Ident {
+    Ident { name: name.to_string(), span: Span::new(start, start + name.len()) }
+}
+
+fn number(n: f64, span: Span) -> Expr {
+    Expr { kind: ExprKind::Number(n), span }
+}
+
+#[test]
+fn parse_main() {
+    let program = parse(include_str!("../../main.scad")).unwrap();
+
+    assert_eq!(program.statements.len(), 3);
+    assert!(matches!(&program.statements[0].kind, StatementKind::Instantiation { name, .. } if name.name == "Logo"));
+    let module = match &program.statements[1].kind {
+        StatementKind::Module(module) => module,
+        other => panic!("expected module, got {:?}", other),
+    };
+    assert_eq!(module.name.name, "Logo");
+    assert_eq!(module.parameters.len(), 2);
+    assert_eq!(module.parameters[1].name.name, "$fn");
+    assert_eq!(module.parameters[1].default, Some(number(100.0, Span::new(36, 39))));
+    let body = match &module.body.kind {
+        StatementKind::Block(body) => body,
+        other => panic!("expected block, got {:?}", other),
+    };
+    assert_eq!(body.len(), 3);
+    let difference = match &body[2].kind {
+        StatementKind::Instantiation { name, child, .. } if name.name == "difference" => child,
+        other => panic!("expected difference, got {:?}", other),
+    };
+    match &difference.kind {
+        StatementKind::Block(children) => {
+            assert_eq!(children.len(), 4);
+            assert!(matches!(children[2].kind, StatementKind::Modifier { modifier: Modifier::Debug, .. }));
+        }
+        other => panic!("expected block, got {:?}", other),
+    }
+    assert!(matches!(&program.statements[2].kind, StatementKind::Echo { arguments, .. } if arguments[0].name.as_ref().unwrap().name == "version"));
+}
+
+#[test]
+fn parse_statements() {
+    let program = parse("include <a.scad>\nuse <b.scad>\nx = 1;\nfunction f(a, b = 2) = a + b;\n;").unwrap();
+    let kinds: Vec<_> = program.statements.iter().map(|s| &s.kind).collect();
+
+    assert_eq!(kinds[0], &StatementKind::Include("a.scad".to_string()));
+    assert_eq!(kinds[1], &StatementKind::Use("b.scad".to_string()));
+    assert_eq!(
+        program.statements[2],
+        Statement {
+            kind: StatementKind::Assignment(Assignment {
+                name: ident("x", 30),
+                value: number(1.0, Span::new(34, 35)),
+                span: Span::new(30, 35),
+            }),
+            span: Span::new(30, 36),
+        }
+    );
+    let function = match kinds[3] {
+        StatementKind::Function(function) => function,
+        other => panic!("expected function, got {:?}", other),
+    };
+    assert_eq!(function.name, ident("f", 46));
+    assert_eq!(function.parameters[0], Parameter { name: ident("a", 48), default: None, span: Span::new(48, 49) });
+    assert_eq!(function.parameters[1].span, Span::new(51, 56));
+    assert_eq!(
+        function.body.kind,
+        ExprKind::Binary {
+            op: BinaryOp::Add,
+            lhs: Box::new(Expr { kind: ExprKind::Variable("a".to_string()), span: Span::new(60, 61) }),
+            rhs: Box::new(Expr { kind: ExprKind::Variable("b".to_string()), span: Span::new(64, 65) }),
+        }
+    );
+    assert_eq!(kinds[4], &StatementKind::Empty);
+}
+
+#[test]
+fn parse_control() {
+    let program = parse(
+        "if (a) cube(); else if (b) { sphere(); } else *cylinder();\n\
+         for (i = [1, 2], j = 3) translate([i, j]) cube();\n\
+         let (x = 2) intersection_for (y = [x]) !%sphere(y);\n\
+         assert(x, \"message\") echo(x);",
+    )
+    .unwrap();
+    let kinds: Vec<_> = program.statements.iter().map(|s| &s.kind).collect();
+
+    match kinds[0] {
+        StatementKind::If { otherwise: Some(otherwise), .. } => match &otherwise.kind {
+            StatementKind::If { then, otherwise: Some(otherwise), .. } => {
+                assert!(matches!(then.kind, StatementKind::Block(_)));
+                assert!(matches!(otherwise.kind, StatementKind::Modifier { modifier: Modifier::Disable, .. }));
+            }
+            other => panic!("expected if, got {:?}", other),
+        },
+        other => panic!("expected if, got {:?}", other),
+    }
+    match kinds[1] {
+        StatementKind::For { assignments, child } => {
+            assert_eq!(assignments.len(), 2);
+            assert!(matches!(child.kind, StatementKind::Instantiation { .. }));
+            assert_eq!(&program.statements[1].span, &Span::new(59, 108));
+        }
+        other => panic!("expected for, got {:?}", other),
+    }
+    match kinds[2] {
+        StatementKind::Let { child, .. } => match &child.kind {
+            StatementKind::IntersectionFor { child, .. } => match &child.kind {
+                StatementKind::Modifier { modifier: Modifier::Root, child } => {
+                    assert!(matches!(child.kind, StatementKind::Modifier { modifier: Modifier::Background, .. }))
+                }
+                other => panic!("expected modifier, got {:?}", other),
+            },
+            other => panic!("expected intersection_for, got {:?}", other),
+        },
+        other => panic!("expected let, got {:?}", other),
+    }
+    match kinds[3] {
+        StatementKind::Assert { arguments, child } => {
+            assert_eq!(arguments.len(), 2);
+            assert!(matches!(child.kind, StatementKind::Echo { .. }));
+        }
+        other => panic!("expected assert, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        parse("cube(1;"),
+        Err(ParseError::Unclosed {
+            delimiter: '(',
+            open: Span::new(4, 5),
+            line: 1,
+            column: 5,
+            span: Span::new(6, 7),
+        })
+    );
+    assert_eq!(
+        parse("module m() {").unwrap_err().to_string(),
+        "expected `}` to close `{` opened at 1:12"
+    );
+    assert_eq!(parse("x = ;").unwrap_err().to_string(), "expected expression, found `;`");
+    assert_eq!(parse("translate() x = 1;").unwrap_err().to_string(), "expected statement, found `x`");
+    assert_eq!(parse("cube(1)").unwrap_err().to_string(), "expected `;`, found end of file");
+    assert_eq!(parse("module m() { cube(1) }").unwrap_err().to_string(), "expected `;`, found `}`");
+    assert_eq!(parse("cube(); }").unwrap_err().to_string(), "expected statement, found `}`");
+    assert_eq!(
+        parse("x = \"a"),
+        Err(ParseError::Lex(LexError::UnterminatedString { span: Span::new(4, 5) }))
+    );
+}
+
+#[test]
+fn parse_recovery() {
+    let src = "cube(1;\nx = ;\nmodule m() {\n  sphere(r = , 2);\n  v = [1, +, 3];\n}\ntranslate([0, 0, 1) cylinder();\ny = 2;\n";
+    let (program, errors) = parse_recovering(src);
+
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        [
+            "expected `)` to close `(` opened at 1:5",
+            "expected expression, found `;`",
+            "expected expression, found `,`",
+            "expected expression, found `,`",
+            "expected `]` to close `[` opened at 7:11",
+        ]
+    );
+
+    let kinds: Vec<_> = program.statements.iter().map(|s| &s.kind).collect();
+    assert_eq!(kinds.len(), 5);
+    assert_eq!(*kinds[0], StatementKind::Error);
+    assert_eq!(program.statements[0].span, Span::new(0, 7));
+    assert_eq!(*kinds[1], StatementKind::Error);
+    let body = match kinds[2] {
+        StatementKind::Module(module) => match &module.body.kind {
+            StatementKind::Block(body) => body,
+            other => panic!("expected block, got {:?}", other),
+        },
+        other => panic!("expected module, got {:?}", other),
+    };
+    match &body[0].kind {
+        StatementKind::Instantiation { arguments, .. } => {
+            assert_eq!(arguments.len(), 2);
+            assert_eq!(arguments[0].value.kind, ExprKind::Error);
+            assert_eq!(arguments[0].span, Span::new(36, 39));
+        }
+        other => panic!("expected instantiation, got {:?}", other),
+    }
+    match &body[1].kind {
+        StatementKind::Assignment(assignment) => assert_eq!(sexpr(&assignment.value), "[1 <error> 3]"),
+        other => panic!("expected assignment, got {:?}", other),
+    }
+    match kinds[3] {
+        StatementKind::Instantiation { arguments, child, .. } => {
+            assert_eq!(arguments[0].value.kind, ExprKind::Error);
+            assert!(matches!(&child.kind, StatementKind::Instantiation { name, .. } if name.name == "cylinder"));
+        }
+        other => panic!("expected instantiation, got {:?}", other),
+    }
+    assert!(matches!(kinds[4], StatementKind::Assignment(a) if a.name.name == "y"));
+
+    // lex errors are reported along with parse errors, in source order, but
+    // not the parse errors they cause
+    let (program, errors) = parse_recovering("a = 1 @ 2;\nb = (3;\nc = @;\n@ cube();\nd = \"e");
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        [
+            "unexpected character `@`",
+            "expected `)` to close `(` opened at 2:5",
+            "unexpected character `@`",
+            "unexpected character `@`",
+            "unterminated string literal",
+        ]
+    );
+    let kinds: Vec<_> = program.statements.iter().map(|s| &s.kind).collect();
+    assert_eq!(kinds.len(), 6);
+    assert!(matches!(kinds[2], StatementKind::Assignment(a) if a.value.kind == ExprKind::Error));
+    assert_eq!(*kinds[3], StatementKind::Error);
+    assert!(matches!(kinds[4], StatementKind::Instantiation { .. }));
+
+    // the `;` of a C-style `for` header do not end the statement
+    let (program, errors) = parse_recovering("v = [for (i = 0; i < ; i = i + 1) i];\ncube(1);");
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(messages, ["expected expression, found `;`"]);
+    assert_eq!(program.statements.len(), 2);
+    match &program.statements[0].kind {
+        StatementKind::Assignment(assignment) => assert_eq!(sexpr(&assignment.value), "[<error>]"),
+        other => panic!("expected assignment, got {:?}", other),
+    }
+    let (program, errors) = parse_recovering("x = [for (;;) 1];");
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(messages, ["expected expression, found `;`"]);
+    assert_eq!(program.statements.len(), 1);
+}
+
+/// Renders an expression as an s-expression, to compare trees compactly.
+fn sexpr(expr: &Expr) -> String {
+    fn list(exprs: &[Expr]) -> String {
+        exprs.iter().map(sexpr).collect::<Vec<_>>().join(" ")
+    }
+    fn arguments(arguments: &[Argument]) -> String {
+        arguments
+            .iter()
+            .map(|a| match &a.name {
+                Some(name) => format!("{}={}", name.name, sexpr(&a.value)),
+                None => sexpr(&a.value),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    fn assignments(assignments: &[Assignment]) -> String {
+        assignments
+            .iter()
+            .map(|a| format!("{}={}", a.name.name, sexpr(&a.value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    fn body(body: &Option<Box<Expr>>) -> String {
+        body.as_ref().map_or(String::new(), |body| format!(" {}", sexpr(body)))
+    }
+
+    match &expr.kind {
+        ExprKind::Number(n) => n.to_string(),
+        ExprKind::String(s) => format!("{:?}", s),
+        ExprKind::Bool(b) => b.to_string(),
+        ExprKind::Undef => "undef".to_string(),
+        ExprKind::Variable(name) => name.clone(),
+        ExprKind::Vector(items) => format!("[{}]", list(items)),
+        ExprKind::Range { start, step: Some(step), end } => {
+            format!("[{} : {} : {}]", sexpr(start), sexpr(step), sexpr(end))
+        }
+        ExprKind::Range { start, step: None, end } => format!("[{} : {}]", sexpr(start), sexpr(end)),
+        ExprKind::Unary { op, operand } => {
+            let op = match op {
+                UnaryOp::Negate => "-",
+                UnaryOp::Plus => "+",
+                UnaryOp::Not => "!",
+            };
+            format!("({} {})", op, sexpr(operand))
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let op = match op {
+                BinaryOp::Add => "+",
+                BinaryOp::Subtract => "-",
+                BinaryOp::Multiply => "*",
+                BinaryOp::Divide => "/",
+                BinaryOp::Modulo => "%",
+                BinaryOp::Power => "^",
+                BinaryOp::Equal => "==",
+                BinaryOp::NotEqual => "!=",
+                BinaryOp::Less => "<",
+                BinaryOp::LessEqual => "<=",
+                BinaryOp::Greater => ">",
+                BinaryOp::GreaterEqual => ">=",
+                BinaryOp::And => "&&",
+                BinaryOp::Or => "||",
+            };
+            format!("({} {} {})", op, sexpr(lhs), sexpr(rhs))
+        }
+        ExprKind::Ternary { condition, then, otherwise } => {
+            format!("(? {} {} {})", sexpr(condition), sexpr(then), sexpr(otherwise))
+        }
+        ExprKind::Call { callee, arguments: args } => format!("({} {})", sexpr(callee), arguments(args)),
+        ExprKind::FunctionLiteral { parameters, body } => {
+            let parameters: Vec<_> = parameters
+                .iter()
+                .map(|p| match &p.default {
+                    Some(default) => format!("{}={}", p.name.name, sexpr(default)),
+                    None => p.name.name.clone(),
+                })
+                .collect();
+            format!("(function ({}) {})", parameters.join(" "), sexpr(body))
+        }
+        ExprKind::Index { object, index } => format!("([] {} {})", sexpr(object), sexpr(index)),
+        ExprKind::Member { object, name } => format!("(. {} {})", sexpr(object), name.name),
+        ExprKind::Let { assignments: a, body } => format!("(let ({}) {})", assignments(a), sexpr(body)),
+        ExprKind::Assert { arguments: args, body: b } => format!("(assert ({}){})", arguments(args), body(b)),
+        ExprKind::Echo { arguments: args, body: b } => format!("(echo ({}){})", arguments(args), body(b)),
+        ExprKind::ComprehensionFor { assignments: a, body } => format!("(for ({}) {})", assignments(a), sexpr(body)),
+        ExprKind::ComprehensionCFor { init, condition, update, body } => format!(
+            "(for ({}; {}; {}) {})",
+            assignments(init),
+            sexpr(condition),
+            assignments(update),
+            sexpr(body)
+        ),
+        ExprKind::ComprehensionIf { condition, then, otherwise } => match otherwise {
+            Some(otherwise) => format!("(if {} {} {})", sexpr(condition), sexpr(then), sexpr(otherwise)),
+            None => format!("(if {} {})", sexpr(condition), sexpr(then)),
+        },
+        ExprKind::Each(body) => format!("(each {})", sexpr(body)),
+        ExprKind::Error => "<error>".to_string(),
+    }
+}
+
+/// Parses `src` as the value of an assignment.
+fn parse_expr(src: &str) -> Result<Expr, ParseError> {
+    let program = parse(&format!("x = {};", src))?;
+    match program.statements.into_iter().next().map(|s| s.kind) {
+        Some(StatementKind::Assignment(assignment)) => Ok(assignment.value),
+        other => panic!("expected assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_precedence() {
+    // mirrors the operator tests of the upstream grammar
+    let table = [
+        ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+        ("1 * 2 + 3", "(+ (* 1 2) 3)"),
+        ("1 - 2 - 3", "(- (- 1 2) 3)"),
+        ("8 / 4 / 2", "(/ (/ 8 4) 2)"),
+        ("7 % 3 * 2", "(* (% 7 3) 2)"),
+        ("(1 + 2) * 3", "(* (+ 1 2) 3)"),
+        ("2 ^ 3 ^ 2", "(^ 2 (^ 3 2))"),
+        ("2 * 3 ^ 2", "(* 2 (^ 3 2))"),
+        ("-2 ^ 2", "(- (^ 2 2))"),
+        ("2 ^ -1", "(^ 2 (- 1))"),
+        ("2 ^ -1 ^ 2", "(^ 2 (- (^ 1 2)))"),
+        ("-a * b", "(* (- a) b)"),
+        ("- - a", "(- (- a))"),
+        ("+a - -b", "(- (+ a) (- b))"),
+        ("!a && b", "(&& (! a) b)"),
+        ("!a == b", "(== (! a) b)"),
+        ("!!a", "(! (! a))"),
+        ("a || b && c", "(|| a (&& b c))"),
+        ("a && b || c", "(|| (&& a b) c)"),
+        ("a || b || c", "(|| (|| a b) c)"),
+        ("a == b != c", "(!= (== a b) c)"),
+        ("a < b == c > d", "(== (< a b) (> c d))"),
+        ("a < b < c", "(< (< a b) c)"),
+        ("a <= b + 1", "(<= a (+ b 1))"),
+        ("a >= b && c != d", "(&& (>= a b) (!= c d))"),
+        ("a + b < c * d", "(< (+ a b) (* c d))"),
+        ("a ? b : c", "(? a b c)"),
+        ("a ? b : c ? d : e", "(? a b (? c d e))"),
+        ("a ? b ? c : d : e", "(? a (? b c d) e)"),
+        ("a || b ? c + 1 : d", "(? (|| a b) (+ c 1) d)"),
+        ("a[1][2]", "([] ([] a 1) 2)"),
+        ("a[i + 1] * 2", "(* ([] a (+ i 1)) 2)"),
+        ("v.x + v.y", "(+ (. v x) (. v y))"),
+        ("-v.x ^ 2", "(- (^ (. v x) 2))"),
+        ("p[0].x", "(. ([] p 0) x)"),
+        ("f(1, b = 2)[0]", "([] (f 1 b=2) 0)"),
+        ("f() + g(h(1))", "(+ (f ) (g (h 1)))"),
+        ("[1, 2 + 3, [4],]", "[1 (+ 2 3) [4]]"),
+        ("[]", "[]"),
+        ("[0 : 10]", "[0 : 10]"),
+        ("[0 : 2 : 10]", "[0 : 2 : 10]"),
+        ("[a - 1 : -b]", "[(- a 1) : (- b)]"),
+        ("[1, 2][0]", "([] [1 2] 0)"),
+        ("true && !undef", "(&& true (! undef))"),
+        ("\"a\" == str(1.5e3 - .5)", "(== \"a\" (str (- 1500 0.5)))"),
+        ("let (a = 1, b = a) a + b", "(let (a=1 b=a) (+ a b))"),
+        ("a ? let (x = 1) x : 0", "(? a (let (x=1) x) 0)"),
+        ("assert(a > 0) a * 2", "(assert ((> a 0)) (* a 2))"),
+        ("echo(\"x\", y = 1)", "(echo (\"x\" y=1))"),
+        ("echo(a) assert(b) let (c = 1) c", "(echo (a) (assert (b) (let (c=1) c)))"),
+    ];
+    for (src, expected) in table.iter() {
+        let expr = parse_expr(src).unwrap_or_else(|err| panic!("{}: {}", src, err));
+        assert_eq!(sexpr(&expr), *expected, "{}", src);
+    }
+
+    let expr = parse_expr("-a[1] + 2").unwrap();
+    assert_eq!(expr.span, Span::new(4, 13));
+    match expr.kind {
+        ExprKind::Binary { lhs, .. } => assert_eq!(lhs.span, Span::new(4, 9)),
+        other => panic!("expected binary, got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_expr_errors() {
+    let errors = [
+        ("1 + let (a = 1) a", "expected expression, found `let`"),
+        ("-echo(1)", "expected expression, found `echo`"),
+        ("a ? b", "expected `:`, found `;`"),
+        ("[1 : 2", "expected `]` to close `[` opened at 1:5"),
+        ("[1 : 2 : 3 : 4]", "expected `]` to close `[` opened at 1:5"),
+        ("[1, 2", "expected `]` to close `[` opened at 1:5"),
+        ("v.", "expected identifier, found `;`"),
+        ("a[]", "expected expression, found `]`"),
+        ("1 2", "expected `;`, found `2`"),
+    ];
+    for (src, expected) in errors.iter() {
+        assert_eq!(parse_expr(src).unwrap_err().to_string(), *expected, "{}", src);
+    }
+}
+
+#[test]
+fn parse_comprehensions() {
+    let table = [
+        ("[for (i = [0:n]) if (i % 2) each f(i)]", "[(for (i=[0 : n]) (if (% i 2) (each (f i))))]"),
+        ("[for (i = a, j = b) [i, j]]", "[(for (i=a j=b) [i j])]"),
+        ("[for (i = 0; i < n; i = i + 1) i * 2]", "[(for (i=0; (< i n); i=(+ i 1)) (* i 2))]"),
+        ("[for (i = 0, j = 1; i < n; i = i + 1, j = j * 2) j]", "[(for (i=0 j=1; (< i n); i=(+ i 1) j=(* j 2)) j)]"),
+        ("[if (a) 1 else 2]", "[(if a 1 2)]"),
+        ("[if (a) if (b) 1 else 2]", "[(if a (if b 1 2))]"),
+        ("[for (x = v) if (x > 0) x else each [-x, x]]", "[(for (x=v) (if (> x 0) x (each [(- x) x])))]"),
+        ("[for (x = v) let (y = x * x) if (y < 9) y]", "[(for (x=v) (let (y=(* x x)) (if (< y 9) y)))]"),
+        ("[let (a = 1) let (b = 2) for (i = [a:b]) i]", "[(let (a=1) (let (b=2) (for (i=[a : b]) i)))]"),
+        ("[let (a = 1) a + 1]", "[(let (a=1) (+ a 1))]"),
+        ("[0, each v, for (i = v) i, 9]", "[0 (each v) (for (i=v) i) 9]"),
+        ("[(for (i = v) i)]", "[(for (i=v) i)]"),
+        ("[for (i = v) (if (i) i)]", "[(for (i=v) (if i i))]"),
+        ("[each [1, 2]]", "[(each [1 2])]"),
+        ("[for (i = v) [for (j = i) j]]", "[(for (i=v) [(for (j=i) j)])]"),
+        ("[for (i = v) i][0]", "([] [(for (i=v) i)] 0)"),
+    ];
+    for (src, expected) in table.iter() {
+        let expr = parse_expr(src).unwrap_or_else(|err| panic!("{}: {}", src, err));
+        assert_eq!(sexpr(&expr), *expected, "{}", src);
+    }
+
+    let expr = parse_expr("[for (i = v) i]").unwrap();
+    match expr.kind {
+        ExprKind::Vector(items) => assert_eq!(items[0].span, Span::new(5, 18)),
+        other => panic!("expected vector, got {:?}", other),
+    }
+
+    let errors = [
+        ("for (i = v) i", "expected expression, found `for`"),
+        ("1 + [each]", "expected expression, found `]`"),
+        ("[for (i = 0; i < n; i) i]", "expected `=`, found `)`"),
+        ("[for (i = 0; i < n) i]", "expected `;`, found `)`"),
+        ("[for (i = v] i]", "expected `)` to close `(` opened at 1:10"),
+        ("[if (a) 1 else]", "expected expression, found `]`"),
+        ("[for (i = v) i : 2]", "expected `]` to close `[` opened at 1:5"),
+    ];
+    for (src, expected) in errors.iter() {
+        assert_eq!(parse_expr(src).unwrap_err().to_string(), *expected, "{}", src);
+    }
+}
+
+#[test]
+fn parse_function_literals() {
+    let table = [
+        ("function (x) x * 2", "(function (x) (* x 2))"),
+        ("function (a, b = 1) function (c) a + b + c", "(function (a b=1) (function (c) (+ (+ a b) c)))"),
+        ("function () undef", "(function () undef)"),
+        ("g(1)(2)", "((g 1) 2)"),
+        ("f(3)[0](4).x", "(. (([] (f 3) 0) 4) x)"),
+        ("(function (x) x)(2)", "((function (x) x) 2)"),
+        ("fs[i](v) + 1", "(+ (([] fs i) v) 1)"),
+        ("a ? function (x) x : function (x) -x", "(? a (function (x) x) (function (x) (- x)))"),
+        ("map(v, function (x) x * k)", "(map v (function (x) (* x k)))"),
+        ("let (f = function (x) x) f(1)", "(let (f=(function (x) x)) (f 1))"),
+    ];
+    for (src, expected) in table.iter() {
+        let expr = parse_expr(src).unwrap_or_else(|err| panic!("{}: {}", src, err));
+        assert_eq!(sexpr(&expr), *expected, "{}", src);
+    }
+    assert_eq!(parse_expr("1 + function (x) x").unwrap_err().to_string(), "expected expression, found `function`");
+    assert_eq!(parse_expr("function x").unwrap_err().to_string(), "expected `(`, found `x`");
+
+    // a function literal assigned and called like in the OpenSCAD 2021 manual
+    let program = parse("f = function(x) x * 2; echo(f(3));").unwrap();
+    assert_eq!(program.statements.len(), 2);
+    match &program.statements[1].kind {
+        StatementKind::Echo { arguments, .. } => assert_eq!(sexpr(&arguments[0].value), "(f 3)"),
+        other => panic!("expected echo, got {:?}", other),
+    }
+}
+
+#[test]
+fn free_variables() {
+    let captures = |src: &str| parse_expr(src).unwrap().free_variables();
+
+    assert_eq!(captures("function (x) x * k + offset(x)"), ["k", "offset"]);
+    assert_eq!(captures("function (x, y = x0) function (z) x + y + z + w"), ["x0", "w"]);
+    assert_eq!(captures("function (x) let (a = x, b = a * s) b + $fn"), ["s"]);
+    assert_eq!(captures("function (v) [for (i = [0:len(v) - 1], j = [0:i]) v[j] * n]"), ["len", "n"]);
+    assert_eq!(captures("[for (i = 0; i < n; i = i + step) i]"), ["n", "step"]);
+    assert_eq!(captures("function (f) function (x) f(g(x)).y"), ["g"]);
+    assert_eq!(captures("let (a = a) a"), ["a"]);
+    assert_eq!(captures("v.x + v.y"), ["v"]);
+    assert_eq!(captures("function (y) sq(y) + 1"), ["sq"]);
+
+    // called names are captures too, they may hold function literals
+    let called = |src: &str| parse_expr(src).unwrap().called_names();
+    assert_eq!(called("function (x) sq(x) + len(v)"), ["sq", "len"]);
+    assert_eq!(called("function (f) function (x) f(g(x)) + fs[0](x)"), ["g"]);
+}